@@ -0,0 +1,325 @@
+use std::{convert::TryFrom, fs, path::Path};
+
+use anyhow::Context;
+use dialoguer::Password;
+use p12_keystore::KeyStore;
+use pkcs8::EncryptedPrivateKeyInfo;
+use x509_parser::{certificate::X509Certificate, prelude::FromDer};
+
+use super::certificates::KeyPairPem;
+
+/// Reads a certificate chain and its private key for upload. `certificate` may be a
+/// PEM chain, a PEM file holding both the chain and the key, or a PKCS#12
+/// (`.pfx`/`.p12`) bundle; `private_key` is only needed if the key is not in
+/// `certificate`. Encrypted keys and bundles are decrypted with `passphrase`, or else
+/// with a passphrase the user is prompted for.
+///
+/// The result is normalized: the chain runs from the leaf certificate up, without
+/// the root, and the private key is unencrypted PEM.
+pub(crate) fn read(
+    certificate: &Path,
+    private_key: Option<&Path>,
+    passphrase: Option<&str>,
+) -> anyhow::Result<KeyPairPem> {
+    let data = fs::read(certificate)
+        .with_context(|| format!("failed to read {}", certificate.display()))?;
+    let (certs, mut keys) = if is_pem(&data) {
+        read_pem(&data, certificate)?
+    } else {
+        read_pkcs12(&data, certificate, passphrase)?
+    };
+
+    if let Some(path) = private_key {
+        let data = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        if !is_pem(&data) {
+            anyhow::bail!("the private key file {} is not PEM", path.display());
+        }
+        let (key_file_certs, key_file_keys) = read_pem(&data, path)?;
+        if !key_file_certs.is_empty() && (!keys.is_empty() || key_file_keys.is_empty()) {
+            anyhow::bail!(
+                "the private key file contains a certificate - are the certificate and private key files swapped?"
+            );
+        }
+        if !keys.is_empty() {
+            anyhow::bail!(
+                "{} already contains a private key; leave out the private key file",
+                certificate.display()
+            );
+        }
+        keys = key_file_keys;
+    }
+
+    let key = match (keys.pop(), keys.is_empty()) {
+        (Some(key), true) => key,
+        (None, _) => anyhow::bail!(
+            "no private key found; give the private key file, or a file that contains both the certificate and the key"
+        ),
+        (Some(_), false) => anyhow::bail!("more than one private key found"),
+    };
+    let key = match key.tag.as_str() {
+        "ENCRYPTED PRIVATE KEY" => pem::Pem {
+            tag: "PRIVATE KEY".to_owned(),
+            contents: decrypt_key(&key.contents, passphrase)?,
+        },
+        _ => key,
+    };
+
+    Ok(KeyPairPem {
+        public_key: pem::encode_many(&order_chain(certs)?),
+        private_key: pem::encode(&key),
+    })
+}
+
+/// Reads a passphrase from the environment variable given with `--passphrase-from-env`.
+pub(crate) fn passphrase_from_env(var: Option<&str>) -> anyhow::Result<Option<String>> {
+    var.map(|var| {
+        std::env::var(var).with_context(|| {
+            format!(
+                "failed to read the passphrase from environment variable {}",
+                var
+            )
+        })
+    })
+    .transpose()
+}
+
+fn is_pem(data: &[u8]) -> bool {
+    std::str::from_utf8(data).is_ok_and(|text| text.contains("-----BEGIN "))
+}
+
+/// Splits a PEM file into its certificates and private keys.
+fn read_pem(data: &[u8], path: &Path) -> anyhow::Result<(Vec<pem::Pem>, Vec<pem::Pem>)> {
+    let text = String::from_utf8_lossy(data);
+    if text.contains("Proc-Type: 4,ENCRYPTED") {
+        anyhow::bail!(
+            "{} uses the legacy OpenSSL key encryption, which is not supported; convert it with `openssl pkcs8 -topk8 -in {} -out key.pem`",
+            path.display(),
+            path.display()
+        );
+    }
+    let blocks = pem::parse_many(text.as_bytes())
+        .map_err(|e| anyhow::anyhow!("{} is not valid PEM: {}", path.display(), e))?;
+    Ok(blocks
+        .into_iter()
+        .filter(|p| p.tag == "CERTIFICATE" || p.tag.ends_with("PRIVATE KEY"))
+        .partition(|p| p.tag == "CERTIFICATE"))
+}
+
+/// Opens a PKCS#12 bundle, trying an empty passphrase first if none was given.
+fn read_pkcs12(
+    data: &[u8],
+    path: &Path,
+    passphrase: Option<&str>,
+) -> anyhow::Result<(Vec<pem::Pem>, Vec<pem::Pem>)> {
+    let store = match (
+        KeyStore::from_pkcs12(data, passphrase.unwrap_or("")),
+        passphrase,
+    ) {
+        (Ok(store), _) => store,
+        (Err(_), None) => {
+            let passphrase = prompt(path)?;
+            KeyStore::from_pkcs12(data, &passphrase).map_err(|e| {
+                anyhow::anyhow!(
+                    "could not open {} (is the passphrase right?): {}",
+                    path.display(),
+                    e
+                )
+            })?
+        }
+        (Err(e), Some(_)) => anyhow::bail!(
+            "could not open {} as a PKCS#12 bundle (is the passphrase right?): {}",
+            path.display(),
+            e
+        ),
+    };
+    let (_, chain) = store
+        .private_key_chain()
+        .ok_or_else(|| anyhow::anyhow!("{} does not contain a private key", path.display()))?;
+    let certs = chain
+        .chain()
+        .iter()
+        .map(|c| pem::Pem {
+            tag: "CERTIFICATE".to_owned(),
+            contents: c.as_der().to_vec(),
+        })
+        .collect();
+    let key = pem::Pem {
+        tag: "PRIVATE KEY".to_owned(),
+        contents: chain.key().to_vec(),
+    };
+    Ok((certs, vec![key]))
+}
+
+fn decrypt_key(der: &[u8], passphrase: Option<&str>) -> anyhow::Result<Vec<u8>> {
+    let info = EncryptedPrivateKeyInfo::try_from(der)
+        .map_err(|e| anyhow::anyhow!("the encrypted private key could not be parsed: {}", e))?;
+    let passphrase = match passphrase {
+        Some(p) => p.to_owned(),
+        None => Password::new()
+            .with_prompt("Passphrase for the private key")
+            .interact()?,
+    };
+    let document = info.decrypt(passphrase).map_err(|e| {
+        anyhow::anyhow!(
+            "the private key could not be decrypted (is the passphrase right?): {}",
+            e
+        )
+    })?;
+    Ok(document.as_bytes().to_vec())
+}
+
+fn prompt(path: &Path) -> anyhow::Result<String> {
+    Ok(Password::new()
+        .with_prompt(format!("Passphrase for {}", path.display()))
+        .allow_empty_password(true)
+        .interact()?)
+}
+
+/// Puts certificates in order from the leaf up, following issuers, and leaves out
+/// duplicates, the self-signed root and anything that isn't part of the chain.
+fn order_chain(all: Vec<pem::Pem>) -> anyhow::Result<Vec<pem::Pem>> {
+    let mut certs: Vec<pem::Pem> = vec![];
+    for cert in all {
+        if !certs.iter().any(|c| c.contents == cert.contents) {
+            certs.push(cert);
+        }
+    }
+    let mut names = vec![];
+    for (index, cert) in certs.iter().enumerate() {
+        let (_, parsed) = X509Certificate::from_der(&cert.contents)
+            .map_err(|e| anyhow::anyhow!("certificate {} could not be parsed: {}", index + 1, e))?;
+        names.push((parsed.subject().to_string(), parsed.issuer().to_string()));
+    }
+    let is_self_signed = |i: usize| names[i].0 == names[i].1;
+    let issues_another = |i: usize| {
+        (0..names.len()).any(|j| j != i && !is_self_signed(j) && names[j].1 == names[i].0)
+    };
+
+    // A self-signed certificate is only the leaf if there is nothing else; otherwise
+    // it's a root, whether of this chain or of another.
+    let (mut leaves, roots): (Vec<_>, Vec<_>) = (0..names.len())
+        .filter(|i| !issues_another(*i))
+        .partition(|i| !is_self_signed(*i));
+    if leaves.is_empty() {
+        leaves = roots;
+    }
+    let mut leaves = leaves.into_iter();
+    let leaf = match (leaves.next(), leaves.next()) {
+        (Some(leaf), None) => leaf,
+        (None, _) => anyhow::bail!("no certificate found"),
+        (Some(_), Some(_)) => {
+            anyhow::bail!("the certificates do not form a single chain")
+        }
+    };
+
+    let mut order = vec![leaf];
+    let mut current = leaf;
+    while !is_self_signed(current) {
+        match (0..names.len()).find(|j| !order.contains(j) && names[*j].0 == names[current].1) {
+            Some(issuer) => {
+                order.push(issuer);
+                current = issuer;
+            }
+            None => break,
+        }
+    }
+    if order.len() > 1 && is_self_signed(current) {
+        order.pop();
+    }
+
+    let mut certs: Vec<_> = certs.into_iter().map(Some).collect();
+    Ok(order.into_iter().filter_map(|i| certs[i].take()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa};
+
+    fn issue(name: &str, ca: bool, issuer: Option<&Certificate>) -> (Certificate, pem::Pem) {
+        let mut params = CertificateParams::new(vec![format!("{}.example.com", name)]);
+        params.distinguished_name.push(DnType::CommonName, name);
+        if ca {
+            params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        }
+        let cert = Certificate::from_params(params).unwrap();
+        let der = match issuer {
+            Some(issuer) => cert.serialize_der_with_signer(issuer).unwrap(),
+            None => cert.serialize_der().unwrap(),
+        };
+        let block = pem::Pem {
+            tag: "CERTIFICATE".to_owned(),
+            contents: der,
+        };
+        (cert, block)
+    }
+
+    fn chain() -> (Certificate, [pem::Pem; 3]) {
+        let (root, root_pem) = issue("root", true, None);
+        let (intermediate, intermediate_pem) = issue("intermediate", true, Some(&root));
+        let (leaf, leaf_pem) = issue("leaf", false, Some(&intermediate));
+        (leaf, [leaf_pem, intermediate_pem, root_pem])
+    }
+
+    #[test]
+    fn order_chain_puts_the_leaf_first_and_drops_the_root() {
+        let (_, [leaf, intermediate, root]) = chain();
+        let ordered = order_chain(vec![root, intermediate.clone(), leaf.clone()]).unwrap();
+        assert_eq!(ordered, [leaf, intermediate]);
+    }
+
+    #[test]
+    fn order_chain_leaves_out_duplicates_and_unrelated_roots() {
+        let (_, [leaf, intermediate, _]) = chain();
+        let (_, other_root) = issue("other root", true, None);
+        let ordered = order_chain(vec![
+            intermediate.clone(),
+            leaf.clone(),
+            other_root,
+            intermediate.clone(),
+        ])
+        .unwrap();
+        assert_eq!(ordered, [leaf, intermediate]);
+    }
+
+    #[test]
+    fn order_chain_keeps_a_lone_self_signed_certificate() {
+        let (_, cert) = issue("self-signed", false, None);
+        assert_eq!(order_chain(vec![cert.clone()]).unwrap(), [cert]);
+    }
+
+    #[test]
+    fn order_chain_rejects_more_than_one_leaf() {
+        let (root, root_pem) = issue("root", true, None);
+        let (_, first) = issue("first", false, Some(&root));
+        let (_, second) = issue("second", false, Some(&root));
+        let err = order_chain(vec![first, second, root_pem]).unwrap_err();
+        assert!(err.to_string().contains("single chain"));
+        assert!(order_chain(vec![]).is_err());
+    }
+
+    #[test]
+    fn read_detects_swapped_certificate_and_key_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let (leaf, [leaf_pem, ..]) = chain();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        fs::write(&cert_path, pem::encode(&leaf_pem)).unwrap();
+        fs::write(&key_path, leaf.serialize_private_key_pem()).unwrap();
+
+        let err = read(&key_path, Some(&cert_path), None).err().unwrap();
+        assert!(err.to_string().contains("swapped"), "{}", err);
+
+        let both_path = dir.path().join("both.pem");
+        fs::write(
+            &both_path,
+            pem::encode(&leaf_pem) + &leaf.serialize_private_key_pem(),
+        )
+        .unwrap();
+        let err = read(&both_path, Some(&key_path), None).err().unwrap();
+        assert!(err.to_string().contains("already contains a private key"));
+
+        let pair = read(&cert_path, Some(&key_path), None).unwrap();
+        assert_eq!(pair.public_key, pem::encode(&leaf_pem));
+    }
+}
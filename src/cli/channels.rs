@@ -0,0 +1,690 @@
+use hippo_openapi::models::{
+    ChannelDto, ChannelRevisionSelectionStrategy, RevisionDto, UpdateChannelCommand,
+};
+use semver::{Compat, Version, VersionReq};
+use serde::Serialize;
+
+use super::commands::OutputFormat;
+use crate::client::Client;
+
+/// The range rule for channels that don't specify one: the latest release (npm-style
+/// `*` leaves out prereleases).
+pub(crate) const DEFAULT_RANGE_RULE: &str = "*";
+
+/// An update command that leaves all of a channel's settings as they are, to be
+/// changed where needed. The server replaces every setting on update, so anything
+/// left out would be cleared.
+pub(crate) fn update_command(channel: &ChannelDto) -> UpdateChannelCommand {
+    UpdateChannelCommand {
+        id: channel.id.clone(),
+        name: channel.name.clone(),
+        domain: channel.domain.clone(),
+        revision_selection_strategy: channel.revision_selection_strategy,
+        range_rule: channel.range_rule.clone(),
+        active_revision_id: channel.active_revision.as_ref().map(|r| r.id.clone()),
+        certificate_id: channel.certificate.as_ref().map(|c| c.id.clone()),
+    }
+}
+
+/// Pins a channel to a revision, keeping its range rule for when it is unpinned.
+pub(crate) async fn pin(
+    client: &Client,
+    channel: &ChannelDto,
+    revision: &RevisionDto,
+) -> anyhow::Result<()> {
+    let mut command = update_command(channel);
+    command.revision_selection_strategy = ChannelRevisionSelectionStrategy::UseSpecifiedRevision;
+    command.active_revision_id = Some(revision.id.clone());
+    client.update_channel(command).await
+}
+
+/// Describes what a channel is currently set up to serve, e.g. "pinned revision
+/// 1.2.0" or "range rule '^1.2', serving 1.2.3".
+pub(crate) fn describe_selection(channel: &ChannelDto) -> String {
+    let active = channel
+        .active_revision
+        .as_ref()
+        .map(|r| r.revision_number.as_str());
+    match (channel.revision_selection_strategy, active) {
+        (ChannelRevisionSelectionStrategy::UseSpecifiedRevision, Some(revision)) => {
+            format!("pinned revision {}", revision)
+        }
+        (ChannelRevisionSelectionStrategy::UseSpecifiedRevision, None) => {
+            "pinned, but to no revision".to_owned()
+        }
+        (ChannelRevisionSelectionStrategy::UseRangeRule, _) => format!(
+            "range rule '{}', {}",
+            channel.range_rule.as_deref().unwrap_or(DEFAULT_RANGE_RULE),
+            active.map_or_else(
+                || "serving nothing".to_owned(),
+                |r| format!("serving {}", r)
+            )
+        ),
+    }
+}
+
+/// Parses a range rule as Hippo reads it: an npm-style semver range, such as
+/// `^1.2`, `>=1.0.0 <2.0.0`, `1.x` or `~1.2.3 || ^2`. If the rule is invalid, the
+/// error points at the comparator that could not be parsed.
+pub(crate) fn parse_range_rule(rule: &str) -> anyhow::Result<VersionReq> {
+    if let Ok(req) = VersionReq::parse_compat(rule, Compat::Npm) {
+        return Ok(req);
+    }
+    let (start, token) = comparators(rule)
+        .into_iter()
+        .find(|(_, token)| VersionReq::parse_compat(token, Compat::Npm).is_err())
+        .unwrap_or((0, rule.trim_end()));
+    anyhow::bail!(
+        "invalid range rule: '{}' is not a valid version comparator\n  {}\n  {}{}",
+        token,
+        rule,
+        " ".repeat(rule[..start].chars().count()),
+        "^".repeat(token.chars().count().max(1))
+    )
+}
+
+/// Splits a range rule into its comparators (such as `>= 1.0`) and where each one
+/// starts, leaving out the `,`, `||` and `-` that join them.
+fn comparators(rule: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (index, c) in rule
+        .char_indices()
+        .chain(std::iter::once((rule.len(), ' ')))
+    {
+        let separator = c.is_whitespace() || c == ',';
+        match (start, separator) {
+            (None, false) => start = Some(index),
+            (Some(s), true) => {
+                words.push((s, index));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+
+    // An operator written apart from its version (`>= 1.0`) belongs with it.
+    let mut comparators = vec![];
+    let mut operator_start = None;
+    for (start, end) in words {
+        let word = &rule[start..end];
+        if word == "||" || word == "-" {
+            continue;
+        }
+        if word.chars().all(|c| "<>=~^".contains(c)) {
+            operator_start.get_or_insert(start);
+            continue;
+        }
+        let start = operator_start.take().unwrap_or(start);
+        comparators.push((start, &rule[start..end]));
+    }
+    if let Some(start) = operator_start {
+        comparators.push((start, rule[start..].trim_end()));
+    }
+    comparators
+}
+
+/// The app's revisions whose revision numbers are valid semver versions, newest first.
+pub(crate) fn versions_of<'a>(
+    revisions: &'a [RevisionDto],
+    app_id: &str,
+) -> Vec<(Version, &'a RevisionDto)> {
+    let mut versions: Vec<_> = revisions
+        .iter()
+        .filter(|r| r.app_id == app_id)
+        .filter_map(|r| Version::parse(&r.revision_number).ok().map(|v| (v, r)))
+        .collect();
+    versions.sort_by(|a, b| b.0.cmp(&a.0));
+    versions
+}
+
+/// The release the app had before `current`: the newest revision with a lower
+/// version that is not a prerelease.
+pub(crate) fn previous_revision<'a>(
+    revisions: &'a [RevisionDto],
+    app_id: &str,
+    current: &RevisionDto,
+) -> anyhow::Result<Option<&'a RevisionDto>> {
+    let current_version = Version::parse(&current.revision_number).map_err(|_| {
+        anyhow::anyhow!(
+            "revision {} is not a semver version, so the previous revision is not known; pass --to",
+            current.revision_number
+        )
+    })?;
+    Ok(versions_of(revisions, app_id)
+        .into_iter()
+        .find(|(v, _)| *v < current_version && !v.is_prerelease())
+        .map(|(_, r)| r))
+}
+
+/// The command that puts a channel back the way it is now, if there is one.
+pub(crate) fn undo_command(channel: &ChannelDto, name: &str, app: Option<&str>) -> Option<String> {
+    let app = app.map_or_else(String::new, |a| format!(" --app {}", a));
+    match channel.revision_selection_strategy {
+        ChannelRevisionSelectionStrategy::UseSpecifiedRevision => channel
+            .active_revision
+            .as_ref()
+            .map(|r| format!("hippo channel pin {} {}{}", name, r.revision_number, app)),
+        ChannelRevisionSelectionStrategy::UseRangeRule => Some(format!(
+            "hippo channel unpin {} --range-rule '{}'{}",
+            name,
+            channel.range_rule.as_deref().unwrap_or(DEFAULT_RANGE_RULE),
+            app
+        )),
+    }
+}
+
+/// Warns if a range rule matches none of the app's revisions, so a channel using it
+/// would have nothing to serve.
+pub(crate) async fn warn_if_unmatched(
+    client: &Client,
+    app_id: &str,
+    rule: &str,
+    req: &VersionReq,
+) -> anyhow::Result<()> {
+    let revisions = client.list_revisions().await?.revisions;
+    let versions = versions_of(&revisions, app_id);
+    if let Some((latest, _)) = versions.first() {
+        if !versions.iter().any(|(v, _)| req.matches(v)) {
+            eprintln!(
+                "Warning: range rule '{}' does not match any of the app's {} revision(s) (the latest is {})",
+                rule,
+                versions.len(),
+                latest
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Which revision a channel (or a range rule) selects, and why.
+#[derive(Serialize)]
+pub(crate) struct Resolution {
+    pub channel: Option<String>,
+    pub strategy: &'static str,
+    pub range_rule: Option<String>,
+    /// The range rule as semver reads it, e.g. `>=1.2.0 <2.0.0` for `^1.2`
+    pub interpreted_as: Option<String>,
+    pub selected: Option<String>,
+    pub reason: String,
+    /// The revision the server says the channel is serving, if it differs.
+    pub active_revision: Option<String>,
+    pub candidates: Vec<Candidate>,
+}
+
+/// One of the app's revisions, as seen by the channel's selection strategy.
+#[derive(Serialize)]
+pub(crate) struct Candidate {
+    pub revision: String,
+    pub id: String,
+    pub matches: bool,
+    pub selected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+/// Works out which of the app's revisions Hippo would select for a range rule: the
+/// highest matching version. As in npm, a prerelease only matches if the rule names a
+/// prerelease of the same major.minor.patch version.
+pub(crate) fn resolve_rule(
+    revisions: &[RevisionDto],
+    app_id: &str,
+    rule: &str,
+) -> anyhow::Result<Resolution> {
+    let req = parse_range_rule(rule)?;
+    let mut candidates: Vec<Candidate> = versions_of(revisions, app_id)
+        .into_iter()
+        .map(|(version, revision)| {
+            let matches = req.matches(&version);
+            let mut release = version.clone();
+            release.pre.clear();
+            let note = if !matches && version.is_prerelease() && req.matches(&release) {
+                Some(format!(
+                    "prerelease: only matched by a rule that names a prerelease of {}, e.g. '>={}-0'",
+                    release, release
+                ))
+            } else {
+                None
+            };
+            Candidate {
+                revision: revision.revision_number.clone(),
+                id: revision.id.clone(),
+                matches,
+                selected: false,
+                note,
+            }
+        })
+        .collect();
+    candidates.extend(unversioned(
+        revisions,
+        app_id,
+        "not a semver version, so never selected by a range rule",
+    ));
+
+    let matching = candidates.iter().filter(|c| c.matches).count();
+    let (selected, reason) = match candidates.iter_mut().find(|c| c.matches) {
+        Some(best) => {
+            best.selected = true;
+            (
+                Some(best.revision.clone()),
+                format!("the highest of {} matching revision(s)", matching),
+            )
+        }
+        None => (
+            None,
+            "no revision matches the range rule, so there is nothing to serve".to_owned(),
+        ),
+    };
+    Ok(Resolution {
+        channel: None,
+        strategy: "range_rule",
+        range_rule: Some(rule.to_owned()),
+        interpreted_as: Some(req.to_string()),
+        selected,
+        reason,
+        active_revision: None,
+        candidates,
+    })
+}
+
+/// Works out which revision a channel selects, using its selection strategy.
+pub(crate) fn resolve_channel(
+    revisions: &[RevisionDto],
+    channel: &ChannelDto,
+) -> anyhow::Result<Resolution> {
+    let active = channel
+        .active_revision
+        .as_ref()
+        .map(|r| r.revision_number.clone());
+    let mut resolution = match channel.revision_selection_strategy {
+        ChannelRevisionSelectionStrategy::UseRangeRule => resolve_rule(
+            revisions,
+            &channel.app_id,
+            channel.range_rule.as_deref().unwrap_or(DEFAULT_RANGE_RULE),
+        )?,
+        ChannelRevisionSelectionStrategy::UseSpecifiedRevision => {
+            let pinned = channel.active_revision.as_ref().map(|r| r.id.as_str());
+            let mut candidates: Vec<Candidate> = versions_of(revisions, &channel.app_id)
+                .into_iter()
+                .map(|(_, r)| Candidate {
+                    revision: r.revision_number.clone(),
+                    id: r.id.clone(),
+                    matches: false,
+                    selected: false,
+                    note: None,
+                })
+                .collect();
+            candidates.extend(unversioned(
+                revisions,
+                &channel.app_id,
+                "not a semver version",
+            ));
+            for candidate in candidates.iter_mut() {
+                candidate.selected = pinned == Some(candidate.id.as_str());
+                candidate.matches = candidate.selected;
+            }
+            Resolution {
+                channel: None,
+                strategy: "specified_revision",
+                range_rule: None,
+                interpreted_as: None,
+                selected: active.clone(),
+                reason: match &active {
+                    Some(_) => "the channel is pinned to this revision".to_owned(),
+                    None => "the channel is pinned, but not to any revision".to_owned(),
+                },
+                active_revision: None,
+                candidates,
+            }
+        }
+    };
+    resolution.channel = Some(channel.name.clone());
+    if active != resolution.selected {
+        resolution.active_revision = active;
+    }
+    Ok(resolution)
+}
+
+/// The app's revisions whose revision numbers are not semver versions.
+fn unversioned(revisions: &[RevisionDto], app_id: &str, note: &str) -> Vec<Candidate> {
+    revisions
+        .iter()
+        .filter(|r| r.app_id == app_id && Version::parse(&r.revision_number).is_err())
+        .map(|r| Candidate {
+            revision: r.revision_number.clone(),
+            id: r.id.clone(),
+            matches: false,
+            selected: false,
+            note: Some(note.to_owned()),
+        })
+        .collect()
+}
+
+impl Resolution {
+    pub(crate) fn print(&self, output: OutputFormat) -> anyhow::Result<()> {
+        match output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+            OutputFormat::Text => {
+                match (&self.channel, &self.range_rule, &self.interpreted_as) {
+                    (Some(name), Some(rule), Some(req)) => {
+                        println!("Channel {} uses range rule '{}' ({})", name, rule, req)
+                    }
+                    (Some(name), _, _) => {
+                        println!("Channel {} is pinned to a specific revision", name)
+                    }
+                    (None, Some(rule), Some(req)) => println!("Range rule '{}' ({})", rule, req),
+                    _ => {}
+                }
+                let width = self
+                    .candidates
+                    .iter()
+                    .map(|c| c.revision.len())
+                    .max()
+                    .unwrap_or(0);
+                for candidate in &self.candidates {
+                    let verdict = match (candidate.selected, candidate.matches) {
+                        (true, _) => "selected",
+                        (false, true) => "matches",
+                        (false, false) => "no match",
+                    };
+                    let line = format!(
+                        "  {} {:width$}  {:8}  {}",
+                        if candidate.selected { "*" } else { " " },
+                        candidate.revision,
+                        verdict,
+                        candidate.note.as_deref().unwrap_or_default(),
+                        width = width
+                    );
+                    println!("{}", line.trim_end());
+                }
+                if self.candidates.is_empty() {
+                    println!("  (the app has no revisions)");
+                }
+                match &self.selected {
+                    Some(revision) => println!("Selected revision {}: {}", revision, self.reason),
+                    None => println!("No revision selected: {}", self.reason),
+                }
+                if let Some(active) = &self.active_revision {
+                    println!(
+                        "Note: the server reports that the channel is serving revision {}",
+                        active
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revision(id: &str, app_id: &str, number: &str) -> RevisionDto {
+        RevisionDto {
+            id: id.to_owned(),
+            app_id: app_id.to_owned(),
+            revision_number: number.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    fn revisions() -> Vec<RevisionDto> {
+        vec![
+            revision("r1", "app", "1.0.0"),
+            revision("r2", "app", "1.2.0"),
+            revision("r3", "app", "1.3.0-dev.1"),
+            revision("r4", "app", "2.0.0"),
+            revision("r5", "app", "nightly"),
+            revision("r6", "other", "9.0.0"),
+        ]
+    }
+
+    fn channel(
+        strategy: ChannelRevisionSelectionStrategy,
+        range_rule: Option<&str>,
+        active: Option<RevisionDto>,
+    ) -> ChannelDto {
+        ChannelDto {
+            id: "c1".to_owned(),
+            app_id: "app".to_owned(),
+            name: "staging".to_owned(),
+            revision_selection_strategy: strategy,
+            range_rule: range_rule.map(str::to_owned),
+            active_revision: active.map(Box::new),
+            ..Default::default()
+        }
+    }
+
+    fn verdicts(resolution: &Resolution) -> Vec<(&str, bool, bool)> {
+        resolution
+            .candidates
+            .iter()
+            .map(|c| (c.revision.as_str(), c.matches, c.selected))
+            .collect()
+    }
+
+    #[test]
+    fn parse_range_rule_accepts_npm_ranges() {
+        for rule in [
+            "*",
+            "^1.2",
+            ">=1.0.0 <2.0.0",
+            "1.x",
+            "~1.2.3 || ^2",
+            ">= 1.0, < 2",
+        ] {
+            assert!(parse_range_rule(rule).is_ok(), "{}", rule);
+        }
+        let req = parse_range_rule(">=1.0.0 <2.0.0").unwrap();
+        assert!(req.matches(&Version::parse("1.5.0").unwrap()));
+        assert!(!req.matches(&Version::parse("2.0.0").unwrap()));
+    }
+
+    #[test]
+    fn parse_range_rule_points_at_the_invalid_comparator() {
+        let err = parse_range_rule(">=1.0 <2.x.y.z").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid range rule: '<2.x.y.z' is not a valid version comparator\n  >=1.0 <2.x.y.z\n        ^^^^^^^^"
+        );
+
+        let err = parse_range_rule("^1 || >= banana").unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("\n  ^1 || >= banana\n        ^^^^^^^^^"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn comparators_splits_on_separators_and_keeps_operators_with_versions() {
+        assert_eq!(
+            comparators(">= 1.0, <2 || ~3 - 4"),
+            [(0, ">= 1.0"), (8, "<2"), (14, "~3"), (19, "4")]
+        );
+        assert_eq!(comparators("1.0 >="), [(0, "1.0"), (4, ">=")]);
+        assert!(comparators("  ").is_empty());
+    }
+
+    #[test]
+    fn resolve_rule_selects_the_highest_match() {
+        let resolution = resolve_rule(&revisions(), "app", "^1").unwrap();
+        assert_eq!(resolution.selected.as_deref(), Some("1.2.0"));
+        assert_eq!(resolution.reason, "the highest of 2 matching revision(s)");
+        assert_eq!(resolution.interpreted_as.as_deref(), Some(">=1.0.0 <2.0.0"));
+        assert_eq!(
+            verdicts(&resolution),
+            [
+                ("2.0.0", false, false),
+                ("1.3.0-dev.1", false, false),
+                ("1.2.0", true, true),
+                ("1.0.0", true, false),
+                ("nightly", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_rule_explains_unmatched_prereleases_and_non_semver_revisions() {
+        let resolution = resolve_rule(&revisions(), "app", "^1").unwrap();
+        let note = |revision: &str| {
+            resolution
+                .candidates
+                .iter()
+                .find(|c| c.revision == revision)
+                .and_then(|c| c.note.clone())
+        };
+        assert_eq!(
+            note("1.3.0-dev.1").as_deref(),
+            Some("prerelease: only matched by a rule that names a prerelease of 1.3.0, e.g. '>=1.3.0-0'")
+        );
+        assert_eq!(
+            note("nightly").as_deref(),
+            Some("not a semver version, so never selected by a range rule")
+        );
+        assert_eq!(note("2.0.0"), None);
+        assert_eq!(note("1.0.0"), None);
+    }
+
+    #[test]
+    fn resolve_rule_selects_a_prerelease_named_by_the_rule() {
+        let resolution = resolve_rule(&revisions(), "app", ">=1.3.0-0 <2").unwrap();
+        assert_eq!(resolution.selected.as_deref(), Some("1.3.0-dev.1"));
+        assert!(resolution
+            .candidates
+            .iter()
+            .all(|c| c.note.is_none() || c.revision == "nightly"));
+    }
+
+    #[test]
+    fn resolve_rule_reports_when_nothing_matches() {
+        let resolution = resolve_rule(&revisions(), "app", "^3").unwrap();
+        assert_eq!(resolution.selected, None);
+        assert!(resolution
+            .candidates
+            .iter()
+            .all(|c| !c.matches && !c.selected));
+        assert!(resolve_rule(&revisions(), "app", "^banana").is_err());
+    }
+
+    #[test]
+    fn resolve_channel_uses_the_range_rule_or_the_default() {
+        let resolution = resolve_channel(
+            &revisions(),
+            &channel(
+                ChannelRevisionSelectionStrategy::UseRangeRule,
+                None,
+                Some(revision("r2", "app", "1.2.0")),
+            ),
+        )
+        .unwrap();
+        assert_eq!(resolution.channel.as_deref(), Some("staging"));
+        assert_eq!(resolution.range_rule.as_deref(), Some(DEFAULT_RANGE_RULE));
+        assert_eq!(resolution.selected.as_deref(), Some("2.0.0"));
+        // The server is still serving the revision selected before 2.0.0 was added.
+        assert_eq!(resolution.active_revision.as_deref(), Some("1.2.0"));
+    }
+
+    #[test]
+    fn resolve_channel_selects_the_pinned_revision() {
+        let pinned = channel(
+            ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
+            Some("^2"),
+            Some(revision("r5", "app", "nightly")),
+        );
+        let resolution = resolve_channel(&revisions(), &pinned).unwrap();
+        assert_eq!(resolution.strategy, "specified_revision");
+        assert_eq!(resolution.range_rule, None);
+        assert_eq!(resolution.selected.as_deref(), Some("nightly"));
+        assert_eq!(resolution.active_revision, None);
+        assert_eq!(
+            verdicts(&resolution),
+            [
+                ("2.0.0", false, false),
+                ("1.3.0-dev.1", false, false),
+                ("1.2.0", false, false),
+                ("1.0.0", false, false),
+                ("nightly", true, true),
+            ]
+        );
+
+        let unpinned = channel(
+            ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
+            None,
+            None,
+        );
+        let resolution = resolve_channel(&revisions(), &unpinned).unwrap();
+        assert_eq!(resolution.selected, None);
+        assert_eq!(
+            resolution.reason,
+            "the channel is pinned, but not to any revision"
+        );
+    }
+
+    #[test]
+    fn previous_revision_is_the_newest_lower_release_of_the_app() {
+        let revisions = revisions();
+        let previous = |number: &str| {
+            previous_revision(&revisions, "app", &revision("x", "app", number))
+                .unwrap()
+                .map(|r| r.revision_number.as_str())
+        };
+        // 1.3.0-dev.1 is skipped as a prerelease, and 9.0.0 belongs to another app.
+        assert_eq!(previous("2.0.0"), Some("1.2.0"));
+        assert_eq!(previous("1.3.0-dev.1"), Some("1.2.0"));
+        assert_eq!(previous("1.2.0"), Some("1.0.0"));
+        assert_eq!(previous("1.0.0"), None);
+        assert_eq!(previous("10.0.0"), Some("2.0.0"));
+    }
+
+    #[test]
+    fn previous_revision_needs_a_semver_current_revision() {
+        let revisions = revisions();
+        let error = previous_revision(&revisions, "app", &revision("r5", "app", "nightly"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(error.contains("pass --to"), "{}", error);
+    }
+
+    #[test]
+    fn undo_command_restores_either_strategy() {
+        let pinned = channel(
+            ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
+            None,
+            Some(revision("r2", "app", "1.2.0")),
+        );
+        assert_eq!(
+            undo_command(&pinned, "staging", None).as_deref(),
+            Some("hippo channel pin staging 1.2.0")
+        );
+        assert_eq!(
+            undo_command(&pinned, "staging", Some("web")).as_deref(),
+            Some("hippo channel pin staging 1.2.0 --app web")
+        );
+
+        let unpinned = channel(
+            ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
+            None,
+            None,
+        );
+        assert_eq!(undo_command(&unpinned, "staging", None), None);
+
+        let ruled = channel(
+            ChannelRevisionSelectionStrategy::UseRangeRule,
+            Some("^1.2"),
+            None,
+        );
+        assert_eq!(
+            undo_command(&ruled, "staging", None).as_deref(),
+            Some("hippo channel unpin staging --range-rule '^1.2'")
+        );
+        let defaulted = channel(ChannelRevisionSelectionStrategy::UseRangeRule, None, None);
+        assert_eq!(
+            undo_command(&defaulted, "staging", Some("web")).as_deref(),
+            Some("hippo channel unpin staging --range-rule '*' --app web")
+        );
+    }
+}
@@ -12,17 +12,82 @@ pub(crate) enum Commands {
     Add {
         /// The name of the certificate
         name: String,
-        /// The filepath to the public key
+        /// The filepath to the public key: a PEM certificate chain, a PEM file with
+        /// both the chain and the private key, or a PKCS#12 (.pfx/.p12) bundle
         #[clap(parse(from_os_str), value_name = "PUBLIC_KEY")]
         public_key_path: PathBuf,
-        /// The filepath to the private key
+        /// The filepath to the private key, if it is not in the public key file
         #[clap(parse(from_os_str), value_name = "PRIVATE_KEY")]
-        private_key_path: PathBuf,
+        private_key_path: Option<PathBuf>,
+        /// Read the passphrase of an encrypted private key or bundle from this
+        /// environment variable instead of prompting for it
+        #[clap(long, value_name = "VAR")]
+        passphrase_from_env: Option<String>,
     },
 
-    // List all certificates
+    /// Generate a TLS certificate for development, and optionally add it
+    #[clap(alias ="gen")]
+    Generate {
+        /// A domain name (or IP address) for the certificate; the first is also its
+        /// common name
+        #[clap(long = "domain", required = true, value_name = "DOMAIN")]
+        domains: Vec<String>,
+        /// Sign the certificate with a development CA, created in the output directory
+        /// if it isn't there yet, instead of self-signing it
+        #[clap(long)]
+        ca: bool,
+        /// How many days the certificate is valid for
+        #[clap(long, default_value = "90")]
+        days: u32,
+        /// The directory to write the certificate and private key to
+        #[clap(long, parse(from_os_str), default_value = ".")]
+        out_dir: PathBuf,
+        /// Add the certificate to Hippo
+        #[clap(long)]
+        upload: bool,
+        /// The name to add the certificate under (defaults to the first domain)
+        #[clap(long)]
+        name: Option<String>,
+        /// Add the certificate and bind it to this channel (ID or name)
+        #[clap(long)]
+        channel: Option<String>,
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// List all certificates, with who they were issued to and when they expire
     List { },
 
+    /// Check that no certificate has expired or will expire soon
+    Check {
+        /// Report certificates that expire within this time (e.g. `30d`, `2w`)
+        #[clap(long, default_value = "30d", validator = humantime::parse_duration)]
+        within: String,
+    },
+
+    /// Replace a TLS certificate, moving the channels that use it to the new one
+    Rotate {
+        /// The certificate (ID or name) to replace
+        old: String,
+        /// The filepath to the new public key (in any format that `add` accepts)
+        #[clap(long, parse(from_os_str), value_name = "PUBLIC_KEY")]
+        public_key: PathBuf,
+        /// The filepath to the new private key, if it is not in the public key file
+        #[clap(long, parse(from_os_str), value_name = "PRIVATE_KEY")]
+        private_key: Option<PathBuf>,
+        /// Read the passphrase of an encrypted private key or bundle from this
+        /// environment variable instead of prompting for it
+        #[clap(long, value_name = "VAR")]
+        passphrase_from_env: Option<String>,
+        /// The name of the new certificate (defaults to the name of the old one)
+        #[clap(long)]
+        name: Option<String>,
+        /// Remove the old certificate once no channel uses it
+        #[clap(long)]
+        remove_old: bool,
+    },
+
     /// Remove a TLS certificate
     #[clap(alias ="delete")]
     #[clap(alias ="rm")]
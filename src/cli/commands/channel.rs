@@ -10,14 +10,16 @@ pub(crate) enum Commands {
         /// The name of the channel
         name: String,
 
-        /// The application ID this channel is bound to
-        app_id: String,
+        /// The application (ID or name) this channel is bound to. Defaults to the
+        /// configured default app
+        app_id: Option<String>,
 
         /// The domain name used to serve requests for this channel
         #[clap(short, long)]
         domain: Option<String>,
 
-        /// if specified, informs hippo to deploy a revision that matches this rule
+        /// if specified, informs hippo to deploy a revision that matches this rule.
+        /// Defaults to `*` (the latest release) if no revision ID is given either
         #[clap(long)]
         range_rule: Option<String>,
 
@@ -33,6 +35,135 @@ pub(crate) enum Commands {
     // List all channels
     List { },
 
+    /// Pin a channel to a specific revision
+    Pin {
+        /// The channel (ID or name)
+        channel: String,
+
+        /// The revision (number or ID) the channel should serve
+        revision: String,
+
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Unpin a channel, so that it serves the latest revision matching a range rule
+    Unpin {
+        /// The channel (ID or name)
+        channel: String,
+
+        /// The range rule to use. Defaults to the rule the channel had before it was
+        /// pinned, or `*` (the latest release)
+        #[clap(long)]
+        range_rule: Option<String>,
+
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Pin a channel to the revision that another channel is serving, e.g. to
+    /// promote what is on staging to production
+    Promote {
+        /// The channel (ID or name) to promote from
+        from: String,
+
+        /// The channel (ID or name) to promote to
+        to: String,
+
+        /// Also copy the source channel's environment variables where the target
+        /// channel's are missing or different
+        #[clap(long)]
+        copy_env: bool,
+
+        /// Promote without asking for confirmation
+        #[clap(short, long)]
+        yes: bool,
+
+        /// The application (ID or name) the channels belong to, if they are given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Pin a channel to the release before the one it is serving, e.g. after a bad deploy
+    Rollback {
+        /// The channel (ID or name)
+        channel: String,
+
+        /// The revision (number or ID) to roll back to. Defaults to the newest release
+        /// older than the one the channel is serving
+        #[clap(long)]
+        to: Option<String>,
+
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Wait until a channel is serving a revision, e.g. after adding the revision
+    Wait {
+        /// The channel (ID or name)
+        channel: String,
+
+        /// The revision (number or ID) to wait for
+        #[clap(long)]
+        revision: String,
+
+        /// How long to wait before giving up with exit code 124, e.g. `90s` or `5m`
+        #[clap(long, default_value = "5m", validator = humantime::parse_duration)]
+        timeout: String,
+
+        /// How often to check the channel
+        #[clap(long, default_value = "2s", validator = humantime::parse_duration)]
+        interval: String,
+
+        /// Also wait until a request to the channel's domain succeeds
+        #[clap(long)]
+        probe: bool,
+
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Check that a channel's app responds as expected, e.g. before promoting it
+    Probe {
+        /// The channel (ID or name)
+        channel: String,
+
+        /// A path to request; give it more than once to check several paths
+        #[clap(long = "path", value_name = "PATH", default_value = "/")]
+        paths: Vec<String>,
+
+        /// The status every response must have (by default any success status)
+        #[clap(long)]
+        status: Option<u16>,
+
+        /// A regular expression every response body must match
+        #[clap(long, value_name = "REGEX", parse(try_from_str = regex::Regex::new))]
+        body: Option<regex::Regex>,
+
+        /// The platform domain, for channels without a domain of their own
+        #[clap(long)]
+        platform_domain: Option<String>,
+
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Show which revision a channel, or a range rule, selects and why
+    Resolve {
+        /// The channel (ID or name), or a range rule such as `^1.2`
+        channel_or_rule: String,
+
+        /// The application (ID or name) whose revisions to consider. Defaults to the
+        /// configured default app
+        #[clap(long)]
+        app: Option<String>,
+    },
+
     /// Remove a channel
     #[clap(alias ="delete")]
     #[clap(alias ="rm")]
@@ -0,0 +1,59 @@
+use clap::{ArgEnum, Subcommand};
+
+#[derive(Subcommand)]
+#[clap(alias ="cfg")]
+pub(crate) enum Commands {
+    /// Show all settings (the login token is redacted)
+    #[clap(alias ="show")]
+    View { },
+
+    /// Print the value of a setting
+    Get {
+        /// The setting to print
+        #[clap(arg_enum)]
+        key: Key,
+    },
+
+    /// Change the value of a setting
+    Set {
+        /// The setting to change
+        #[clap(arg_enum)]
+        key: Key,
+        /// The new value
+        value: String,
+    },
+
+    /// Reset a setting to its default value
+    #[clap(alias ="rm")]
+    Unset {
+        /// The setting to reset
+        #[clap(arg_enum)]
+        key: Key,
+    },
+
+    /// Print the path of the config file in use
+    Path { },
+}
+
+/// The settings that can be viewed and edited with `hippo config`
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+#[clap(rename_all = "snake_case")]
+pub(crate) enum Key {
+    /// The URL of the Hippo server
+    Url,
+    /// The user name of the current login (read-only)
+    Username,
+    /// Whether invalid TLS certificates are accepted from the server
+    DangerAcceptInvalidCerts,
+    /// The output format used when `--output` is not given
+    Output,
+    /// The application (ID or name) used when a command's app is not given
+    DefaultApp,
+    /// The channel (ID or name) used when a command's channel is not given
+    DefaultChannel,
+    /// The program that stores login tokens instead of the config file
+    CredentialHelper,
+    /// Comma-separated patterns (such as `*_PASSWORD`) of environment variable keys
+    /// whose values are never displayed
+    SecretKeys,
+}
@@ -1,4 +1,6 @@
-use clap::Subcommand;
+use std::path::PathBuf;
+
+use clap::{ArgEnum, Subcommand};
 
 #[derive(Subcommand)]
 #[clap(alias ="e")]
@@ -12,14 +14,112 @@ pub(crate) enum Commands {
     Add {
         /// The environment variable key
         key: String,
-        /// The environment variable value
-        value: String,
-        /// The channel ID this environment variable will be bound to
-        channel_id: String,
+        /// The environment variable value. Leave it out, and give the channel with
+        /// --channel, if the value is read with one of the --value-* options
+        value: Option<String>,
+        /// The channel (ID or name) this environment variable will be bound to. Defaults
+        /// to the configured default channel
+        channel_id: Option<String>,
+        /// The channel (ID or name), for use with the --value-* options instead of the
+        /// CHANNEL_ID argument
+        #[clap(long, value_name = "CHANNEL", conflicts_with = "channel-id")]
+        channel: Option<String>,
+        /// Read the value from a file (a single trailing newline is removed)
+        #[clap(long, parse(from_os_str), value_name = "FILE", group = "value_source")]
+        value_from_file: Option<PathBuf>,
+        /// Read the value from stdin (a single trailing newline is removed)
+        #[clap(long, group = "value_source")]
+        value_stdin: bool,
+        /// Take the value from an environment variable of this process
+        #[clap(long, value_name = "NAME", group = "value_source")]
+        value_from_env: Option<String>,
+    },
+
+    /// Copy environment variables from one channel to another
+    Copy {
+        /// The channel (ID or name) to copy from
+        from: String,
+        /// The channel (ID or name) to copy to
+        to: String,
+        /// Copy only keys matching these patterns (e.g. `DB_*`)
+        #[clap(long, use_value_delimiter = true, value_name = "PATTERN")]
+        only: Vec<String>,
+        /// Do not copy keys matching these patterns
+        #[clap(long, use_value_delimiter = true, value_name = "PATTERN")]
+        exclude: Vec<String>,
+        /// Replace the values of keys the target channel already has
+        #[clap(long)]
+        overwrite: bool,
+        /// Show the changes and the requests that would make them, without making them
+        #[clap(long)]
+        dry_run: bool,
+        /// Show values in the diff instead of masking them (values of secret keys stay
+        /// masked)
+        #[clap(long)]
+        reveal: bool,
+        /// The application (ID or name) the channels belong to, if they are given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Write a channel's environment variables to a file or stdout (secret keys are
+    /// left out)
+    Export {
+        /// The channel (ID or name) whose environment variables to export. Defaults to
+        /// the configured default channel
+        channel: Option<String>,
+        /// The format to write
+        #[clap(long, arg_enum, default_value = "dotenv")]
+        format: ExportFormat,
+        /// The file to write to, instead of stdout
+        #[clap(short, long, parse(from_os_str))]
+        file: Option<PathBuf>,
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Add environment variables from a dotenv file
+    Import {
+        /// The channel (ID or name) the environment variables will be bound to. Defaults
+        /// to the configured default channel
+        channel: Option<String>,
+        /// The dotenv file to read
+        #[clap(short, long, parse(from_os_str), default_value = ".env")]
+        file: PathBuf,
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
     },
 
     // List all environment variables
-    List { },
+    List {
+        /// Show values instead of masking them (values of secret keys stay masked)
+        #[clap(long)]
+        reveal: bool,
+    },
+
+    /// Add, update and remove a channel's environment variables to match a dotenv file
+    Sync {
+        /// The channel (ID or name) to update. Defaults to the configured default channel
+        channel: Option<String>,
+        /// The dotenv file to read
+        #[clap(short, long, parse(from_os_str), default_value = ".env")]
+        file: PathBuf,
+        /// Also remove environment variables that are not in the file
+        #[clap(long)]
+        prune: bool,
+        /// Show the changes and the requests that would make them, without making them
+        #[clap(long)]
+        dry_run: bool,
+        /// Show values in the diff instead of masking them (values of secret keys stay
+        /// masked)
+        #[clap(long)]
+        reveal: bool,
+        /// The application (ID or name) the channel belongs to, if it is given by name
+        #[clap(long)]
+        app: Option<String>,
+    },
 
     /// Remove an environment variable
     #[clap(alias ="delete")]
@@ -29,3 +129,15 @@ pub(crate) enum Commands {
         id: String,
     },
 }
+
+/// The file formats that `env export` can write
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ExportFormat {
+    /// KEY=value lines, as read by `env import`
+    Dotenv,
+    /// A JSON object mapping keys to values
+    Json,
+    /// `export KEY='value'` lines for a POSIX shell (keys that aren't valid shell
+    /// variable names are left out)
+    Shell,
+}
@@ -1,10 +1,22 @@
 pub(crate) mod app;
 pub(crate) mod certificate;
 pub(crate) mod channel;
+pub(crate) mod config;
 pub(crate) mod environment_variable;
+pub(crate) mod preview;
 pub(crate) mod revision;
 
-use clap::Subcommand;
+use clap::{ArgEnum, Subcommand};
+use serde::{Deserialize, Serialize};
+
+/// The format in which commands write their results
+#[derive(ArgEnum, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+}
 
 #[derive(Subcommand)]
 pub(crate) enum Commands {
@@ -20,6 +32,10 @@ pub(crate) enum Commands {
     #[clap(subcommand)]
     Channel(channel::Commands),
 
+    /// View and edit hippo settings
+    #[clap(subcommand)]
+    Config(config::Commands),
+
     /// Add, update, and remove environment variables
     #[clap(subcommand)]
     Env(environment_variable::Commands),
@@ -38,11 +54,18 @@ pub(crate) enum Commands {
         /// Should invalid TLS certificates be accepted by the client?
         #[clap(env, short = 'k', long)]
         danger_accept_invalid_certs: bool,
+        /// A credential helper program to store the token in, instead of the config file
+        #[clap(env = "HIPPO_CREDENTIAL_HELPER", long, value_name = "PROGRAM")]
+        credential_helper: Option<String>,
     },
 
     /// End the current Hippo login session
     Logout {},
 
+    /// Create and remove preview channels for git branches
+    #[clap(subcommand)]
+    Preview(preview::Commands),
+
     /// Create a new Hippo account
     Register {
         /// The Hippo URL
@@ -63,6 +86,6 @@ pub(crate) enum Commands {
     #[clap(subcommand)]
     Revision(revision::Commands),
 
-    /// prints the logged in user
+    /// Prints the logged in user and checks the session with the server
     Whoami {},
 }
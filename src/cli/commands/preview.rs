@@ -0,0 +1,54 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+pub(crate) enum Commands {
+    /// Create or update the preview channel for a git branch, pinned to a revision
+    Up {
+        /// The revision (number or ID) the preview channel should serve
+        #[clap(long)]
+        revision: String,
+
+        /// The branch to preview (defaults to the current branch)
+        #[clap(long)]
+        branch: Option<String>,
+
+        /// The application (ID or name) to preview (defaults to the configured app)
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Remove the preview channel for a git branch
+    Down {
+        /// The branch whose preview channel to remove (defaults to the current branch)
+        #[clap(long)]
+        branch: Option<String>,
+
+        /// The application (ID or name) (defaults to the configured app)
+        #[clap(long)]
+        app: Option<String>,
+    },
+
+    /// Remove preview channels whose branches no longer exist
+    Gc {
+        /// Also remove preview channels of branches that have been merged into the
+        /// base branch
+        #[clap(long)]
+        merged: bool,
+
+        /// The branch that branches are merged into
+        #[clap(long, default_value = "main")]
+        base: String,
+
+        /// Show which channels would be removed without removing them
+        #[clap(long)]
+        dry_run: bool,
+
+        /// Remove the channels without asking for confirmation
+        #[clap(short, long)]
+        yes: bool,
+
+        /// The application (ID or name) (defaults to the configured app)
+        #[clap(long)]
+        app: Option<String>,
+    },
+}
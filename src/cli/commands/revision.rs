@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use clap::Subcommand;
 
 #[derive(Subcommand)]
@@ -9,10 +11,18 @@ pub(crate) enum Commands {
     Add {
         /// The storage ID of the Bindle
         app_storage_id: String,
-        /// The revision number uploaded to Bindle
+        /// The revision number uploaded to Bindle (see `hippo revision version`)
         revision_number: String,
     },
 
+    /// Print a revision number generated from the version in HIPPOFACTS and the git
+    /// repository, to push the bindle and add the revision with
+    Version {
+        /// The HIPPOFACTS file to read the version from
+        #[clap(long, parse(from_os_str), default_value = "HIPPOFACTS")]
+        hippofacts: PathBuf,
+    },
+
     // List all revisions
     List { }
 }
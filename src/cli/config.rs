@@ -0,0 +1,414 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use clap::ArgEnum;
+use fs2::FileExt;
+use hippo_openapi::models::TokenInfo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tempfile::NamedTempFile;
+
+use super::commands::{config::Key, OutputFormat};
+
+/// The layout version written to new config files. Bump this and add a step to
+/// `migrate` whenever the layout changes in a way older files can't be read as-is.
+pub(crate) const CONFIG_VERSION: u64 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct HippoConfig {
+    pub version: u64,
+    pub danger_accept_invalid_certs: bool,
+    pub token_info: Option<TokenInfo>,
+    pub username: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_app: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_channel: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub secret_keys: Vec<String>,
+}
+
+impl Default for HippoConfig {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            danger_accept_invalid_certs: false,
+            token_info: None,
+            username: "".to_owned(),
+            url: "http://localhost:5309".to_owned(),
+            credential_helper: None,
+            output: None,
+            default_app: None,
+            default_channel: None,
+            secret_keys: vec![],
+        }
+    }
+}
+
+impl HippoConfig {
+    /// Loads the config file at `path`, or the default configuration if there is no
+    /// such file. Files in an older layout are upgraded and written back.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Self> {
+        let (config, version) = Self::read(path)?;
+        if version < CONFIG_VERSION {
+            return Self::update(path, |_| Ok(()));
+        }
+        Ok(config)
+    }
+
+    /// Changes the config file at `path` with `change`. An advisory lock is held from
+    /// before the file is read until it has been written, so that `hippo` processes
+    /// running at the same time don't lose each other's changes, and the new contents
+    /// are written to a temporary file alongside it and renamed into place, so readers
+    /// never see a partial file. Returns the new config.
+    pub(crate) fn update<F>(path: &Path, change: F) -> anyhow::Result<Self>
+    where
+        F: FnOnce(&mut Self) -> anyhow::Result<()>,
+    {
+        let dir = match path.parent() {
+            Some(d) if !d.as_os_str().is_empty() => d,
+            _ => Path::new("."),
+        };
+        fs::create_dir_all(dir)?;
+
+        let _lock = ConfigLock::acquire(path)?;
+        let (mut config, _) = Self::read(path)?;
+        change(&mut config)?;
+        let mut file = NamedTempFile::new_in(dir)?;
+        serde_json::to_writer(&mut file, &config)?;
+        file.as_file().sync_all()?;
+        file.persist(path)
+            .with_context(|| format!("failed to write config file {}", path.display()))?;
+        Ok(config)
+    }
+
+    /// Reads and upgrades the config file at `path`, returning it with the layout
+    /// version it was written in.
+    fn read(path: &Path) -> anyhow::Result<(Self, u64)> {
+        if !path.exists() {
+            return Ok((Default::default(), CONFIG_VERSION));
+        }
+
+        // TODO: switch from std::fs to tokio::fs once serde_json implements tokio support
+        // https://github.com/serde-rs/json/issues/316
+        let reader = BufReader::new(File::open(path)?);
+        let value: Value = serde_json::from_reader(reader)
+            .with_context(|| format!("failed to parse config file {}", path.display()))?;
+
+        let version = match value.get("version") {
+            None => 0,
+            Some(v) => v.as_u64().with_context(|| {
+                format!("config file {} has an invalid version", path.display())
+            })?,
+        };
+        if version > CONFIG_VERSION {
+            anyhow::bail!(
+                "config file {} was written by a newer version of hippo (config version {}, this version understands up to {})",
+                path.display(),
+                version,
+                CONFIG_VERSION
+            );
+        }
+
+        let config: Self = serde_json::from_value(migrate(value, version)?)
+            .with_context(|| format!("failed to read config file {}", path.display()))?;
+        Ok((config, version))
+    }
+
+    /// Returns the value of a setting for display, or `None` if it is not set.
+    pub(crate) fn get(&self, key: Key) -> Option<String> {
+        match key {
+            Key::Url => Some(self.url.clone()),
+            Key::Username => Some(self.username.clone()).filter(|u| !u.is_empty()),
+            Key::DangerAcceptInvalidCerts => Some(self.danger_accept_invalid_certs.to_string()),
+            Key::Output => self
+                .output
+                .and_then(|o| o.to_possible_value())
+                .map(|v| v.get_name().to_owned()),
+            Key::DefaultApp => self.default_app.clone(),
+            Key::DefaultChannel => self.default_channel.clone(),
+            Key::SecretKeys => Some(self.secret_keys.join(",")).filter(|k| !k.is_empty()),
+            Key::CredentialHelper => self.credential_helper.clone(),
+        }
+    }
+
+    /// Changes a setting, parsing `value` according to the type of the setting.
+    pub(crate) fn set(&mut self, key: Key, value: &str) -> anyhow::Result<()> {
+        match key {
+            Key::Url => {
+                reqwest::Url::parse(value)
+                    .with_context(|| format!("'{}' is not a valid URL", value))?;
+                self.url = value.to_owned();
+            }
+            Key::Username => anyhow::bail!("the username can only be changed with 'hippo login'"),
+            Key::DangerAcceptInvalidCerts => {
+                self.danger_accept_invalid_certs = value
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("expected 'true' or 'false', got '{}'", value))?;
+            }
+            Key::Output => {
+                self.output =
+                    Some(OutputFormat::from_str(value, true).map_err(anyhow::Error::msg)?);
+            }
+            Key::DefaultApp => self.default_app = Some(value.to_owned()),
+            Key::DefaultChannel => self.default_channel = Some(value.to_owned()),
+            Key::CredentialHelper => self.credential_helper = Some(value.to_owned()),
+            Key::SecretKeys => {
+                let patterns: Vec<String> = value
+                    .split(',')
+                    .map(|p| p.trim().to_owned())
+                    .filter(|p| !p.is_empty())
+                    .collect();
+                for pattern in &patterns {
+                    glob::Pattern::new(pattern)
+                        .with_context(|| format!("'{}' is not a valid pattern", pattern))?;
+                }
+                self.secret_keys = patterns;
+            }
+        }
+        Ok(())
+    }
+
+    /// Resets a setting to its default value.
+    pub(crate) fn unset(&mut self, key: Key) -> anyhow::Result<()> {
+        let defaults = Self::default();
+        match key {
+            Key::Url => self.url = defaults.url,
+            Key::Username => anyhow::bail!("use 'hippo logout' to clear the username"),
+            Key::DangerAcceptInvalidCerts => {
+                self.danger_accept_invalid_certs = defaults.danger_accept_invalid_certs
+            }
+            Key::Output => self.output = defaults.output,
+            Key::DefaultApp => self.default_app = defaults.default_app,
+            Key::DefaultChannel => self.default_channel = defaults.default_channel,
+            Key::CredentialHelper => self.credential_helper = defaults.credential_helper,
+            Key::SecretKeys => self.secret_keys = defaults.secret_keys,
+        }
+        Ok(())
+    }
+
+    /// The config as JSON, with the login token replaced by a placeholder.
+    pub(crate) fn redacted(&self) -> anyhow::Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        if let Some(token) = value.pointer_mut("/token_info/token") {
+            if !token.is_null() {
+                *token = REDACTED.into();
+            }
+        }
+        Ok(value)
+    }
+}
+
+pub(crate) const REDACTED: &str = "[REDACTED]";
+
+/// The config file of a login profile, in the `profiles` directory under `dir`.
+/// Profile names can come from a repository's `.hippo` file, so only plain names are
+/// accepted: a path would let the repository pick the config file, and with it the
+/// credential helper that gets run.
+pub(crate) fn profile_path(dir: &Path, profile: &str) -> anyhow::Result<PathBuf> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        anyhow::bail!(
+            "invalid profile name '{}': profile names can only contain letters, digits, '-' and '_'",
+            profile
+        );
+    }
+    Ok(dir.join("profiles").join(format!("{}.json", profile)))
+}
+
+/// Upgrades a config file from layout version `from` to `CONFIG_VERSION`.
+fn migrate(mut value: Value, from: u64) -> anyhow::Result<Value> {
+    let object = value
+        .as_object_mut()
+        .context("config file does not contain a JSON object")?;
+
+    if from < 1 {
+        // Version 0 files predate the version field. Fill in anything a hand-edited
+        // file is missing from the defaults, as version 0 required every field.
+        if let Value::Object(defaults) = serde_json::to_value(HippoConfig::default())? {
+            for (key, default) in defaults {
+                object.entry(key).or_insert(default);
+            }
+        }
+    }
+
+    object.insert("version".to_owned(), CONFIG_VERSION.into());
+    Ok(value)
+}
+
+/// An exclusive advisory lock on a config file, held on a `.lock` file next to it
+/// (the config file itself gets replaced, so can't carry the lock). Released on drop.
+struct ConfigLock {
+    file: File,
+}
+
+impl ConfigLock {
+    fn acquire(config_path: &Path) -> anyhow::Result<Self> {
+        let lock_path = lock_path(config_path);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&lock_path)
+            .with_context(|| format!("failed to open lock file {}", lock_path.display()))?;
+        file.lock_exclusive()
+            .with_context(|| format!("failed to lock {}", lock_path.display()))?;
+        Ok(Self { file })
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+fn lock_path(config_path: &Path) -> PathBuf {
+    let mut name = config_path
+        .file_name()
+        .map(|n| n.to_os_string())
+        .unwrap_or_default();
+    name.push(".lock");
+    config_path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn migrate_fills_in_missing_fields_of_version_0_files() {
+        let migrated =
+            migrate(json!({"url": "https://hippo.example", "username": "u"}), 0).unwrap();
+        assert_eq!(migrated["version"], CONFIG_VERSION);
+        assert_eq!(migrated["url"], "https://hippo.example");
+        assert_eq!(migrated["username"], "u");
+        assert_eq!(migrated["danger_accept_invalid_certs"], false);
+        let config: HippoConfig = serde_json::from_value(migrated).unwrap();
+        assert!(config.token_info.is_none());
+    }
+
+    #[test]
+    fn migrate_leaves_current_files_alone() {
+        let value = json!({"version": CONFIG_VERSION, "url": "u", "username": "", "danger_accept_invalid_certs": true, "token_info": null});
+        assert_eq!(migrate(value.clone(), CONFIG_VERSION).unwrap(), value);
+    }
+
+    #[test]
+    fn migrate_rejects_non_objects() {
+        assert!(migrate(json!([1, 2]), 0).is_err());
+    }
+
+    #[test]
+    fn load_upgrades_and_rewrites_old_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hippo.json");
+        fs::write(&path, r#"{"url": "https://hippo.example", "username": "u", "danger_accept_invalid_certs": false, "token_info": null}"#).unwrap();
+        let config = HippoConfig::load(&path).unwrap();
+        assert_eq!(config.url, "https://hippo.example");
+        let written: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(written["version"], CONFIG_VERSION);
+    }
+
+    #[test]
+    fn load_rejects_files_from_newer_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hippo.json");
+        fs::write(&path, format!(r#"{{"version": {}}}"#, CONFIG_VERSION + 1)).unwrap();
+        let error = HippoConfig::load(&path).err().unwrap().to_string();
+        assert!(error.contains("newer version of hippo"), "{}", error);
+    }
+
+    #[test]
+    fn load_returns_defaults_without_a_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = HippoConfig::load(&dir.path().join("missing.json")).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert!(!dir.path().join("missing.json").exists());
+    }
+
+    #[test]
+    fn update_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("hippo.json");
+        HippoConfig::update(&path, |c| c.set(Key::SecretKeys, "*_TOKEN, API_KEY")).unwrap();
+        let loaded = HippoConfig::load(&path).unwrap();
+        assert_eq!(loaded.secret_keys, vec!["*_TOKEN", "API_KEY"]);
+    }
+
+    #[test]
+    fn update_leaves_the_file_alone_if_the_change_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hippo.json");
+        HippoConfig::update(&path, |c| c.set(Key::DefaultApp, "app")).unwrap();
+        assert!(HippoConfig::update(&path, |c| {
+            c.set(Key::DefaultApp, "other")?;
+            c.set(Key::Output, "not-a-format")
+        })
+        .is_err());
+        let loaded = HippoConfig::load(&path).unwrap();
+        assert_eq!(loaded.default_app.as_deref(), Some("app"));
+    }
+
+    #[test]
+    fn update_waits_for_the_lock_and_keeps_other_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hippo.json");
+        let lock = ConfigLock::acquire(&path).unwrap();
+
+        let writer = {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                HippoConfig::update(&path, |c| c.set(Key::DefaultApp, "app")).unwrap();
+            })
+        };
+        std::thread::sleep(std::time::Duration::from_millis(200));
+        assert!(!writer.is_finished());
+        assert!(!path.exists());
+
+        // A change made while holding the lock must survive the waiting writer's.
+        let mut config = HippoConfig::default();
+        config.set(Key::DefaultChannel, "latest").unwrap();
+        fs::write(&path, serde_json::to_string(&config).unwrap()).unwrap();
+        drop(lock);
+        writer.join().unwrap();
+
+        let loaded = HippoConfig::load(&path).unwrap();
+        assert_eq!(loaded.default_app.as_deref(), Some("app"));
+        assert_eq!(loaded.default_channel.as_deref(), Some("latest"));
+    }
+
+    #[test]
+    fn profile_path_only_accepts_plain_names() {
+        let dir = Path::new("/home/user/.config/hippo");
+        assert_eq!(
+            profile_path(dir, "staging_2-eu").unwrap(),
+            dir.join("profiles").join("staging_2-eu.json")
+        );
+        for name in [
+            "",
+            "/repo/evil",
+            "../evil",
+            "a/b",
+            "a.b",
+            "..",
+            "a\\b",
+            "stagíng",
+        ] {
+            assert!(profile_path(dir, name).is_err(), "{}", name);
+        }
+    }
+}
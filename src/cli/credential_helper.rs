@@ -0,0 +1,128 @@
+use std::process::Stdio;
+
+use hippo_openapi::models::TokenInfo;
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// An external program which stores Hippo tokens on our behalf.
+///
+/// The protocol follows git-credential: the helper is invoked with a single
+/// action argument (`get`, `store` or `erase`) and a JSON request on stdin.
+/// For `get`, the helper writes a JSON object with `token` and (optionally)
+/// `expiration` to stdout, or writes nothing if it holds no token for the
+/// requested server and user.
+pub(crate) struct CredentialHelper {
+    program: String,
+}
+
+#[derive(Serialize)]
+struct CredentialRequest<'a> {
+    url: &'a str,
+    username: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiration: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+struct CredentialResponse {
+    token: Option<String>,
+    expiration: Option<String>,
+}
+
+impl CredentialHelper {
+    pub(crate) fn new(program: &str) -> Self {
+        Self {
+            program: program.to_owned(),
+        }
+    }
+
+    /// Asks the helper for the token stored for the given server and user.
+    pub(crate) async fn get(&self, url: &str, username: &str) -> anyhow::Result<Option<TokenInfo>> {
+        let request = CredentialRequest {
+            url,
+            username,
+            token: None,
+            expiration: None,
+        };
+        let output = self.invoke("get", &request).await?;
+        if output.trim().is_empty() {
+            return Ok(None);
+        }
+        let response: CredentialResponse = serde_json::from_str(&output).map_err(|e| {
+            anyhow::anyhow!(
+                "credential helper '{}' returned invalid JSON: {}",
+                self.program,
+                e
+            )
+        })?;
+        let CredentialResponse { token, expiration } = response;
+        Ok(token.map(|token| TokenInfo {
+            token: Some(token),
+            expiration,
+        }))
+    }
+
+    /// Hands a newly issued token to the helper for safekeeping.
+    pub(crate) async fn store(
+        &self,
+        url: &str,
+        username: &str,
+        token_info: &TokenInfo,
+    ) -> anyhow::Result<()> {
+        let request = CredentialRequest {
+            url,
+            username,
+            token: token_info.token.as_deref(),
+            expiration: token_info.expiration.as_deref(),
+        };
+        self.invoke("store", &request).await?;
+        Ok(())
+    }
+
+    /// Tells the helper to forget the token for the given server and user.
+    pub(crate) async fn erase(&self, url: &str, username: &str) -> anyhow::Result<()> {
+        let request = CredentialRequest {
+            url,
+            username,
+            token: None,
+            expiration: None,
+        };
+        self.invoke("erase", &request).await?;
+        Ok(())
+    }
+
+    async fn invoke(
+        &self,
+        action: &str,
+        request: &CredentialRequest<'_>,
+    ) -> anyhow::Result<String> {
+        let mut child = Command::new(&self.program)
+            .arg(action)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| {
+                anyhow::anyhow!("failed to run credential helper '{}': {}", self.program, e)
+            })?;
+
+        let input = serde_json::to_vec(request)?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(&input).await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "credential helper '{}' failed to {} credentials: {}",
+                self.program,
+                action,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
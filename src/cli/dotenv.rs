@@ -0,0 +1,320 @@
+/// Parses the contents of a dotenv file into key/value pairs, in file order.
+///
+/// Supports `#` comments, an optional `export` prefix, unquoted values (trimmed,
+/// with trailing ` #` comments removed), single-quoted values (taken literally)
+/// and double-quoted values (with `\n`, `\r`, `\t`, `\"`, `\\` and `\$` escapes).
+/// Quoted values may span several lines.
+pub(crate) fn parse(text: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    let mut entries = vec![];
+    while let Some(entry) = parser.next_entry()? {
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_blanks(&mut self) {
+        while matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.bump();
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(c) = self.bump() {
+            if c == '\n' {
+                break;
+            }
+        }
+    }
+
+    fn error<T>(&self, message: &str) -> anyhow::Result<T> {
+        Err(anyhow::anyhow!("line {}: {}", self.line, message))
+    }
+
+    fn next_entry(&mut self) -> anyhow::Result<Option<(String, String)>> {
+        loop {
+            self.skip_blanks();
+            match self.peek() {
+                None => return Ok(None),
+                Some('\n') | Some('\r') => {
+                    self.bump();
+                }
+                Some('#') => self.skip_line(),
+                Some(_) => break,
+            }
+        }
+
+        let mut key = self.read_key();
+        if key == "export" && matches!(self.peek(), Some(' ') | Some('\t')) {
+            self.skip_blanks();
+            key = self.read_key();
+        }
+        if key.is_empty() {
+            return self.error("expected a variable name");
+        }
+        if key.starts_with(|c: char| c.is_ascii_digit()) {
+            return self.error(&format!("'{}' is not a valid variable name", key));
+        }
+
+        self.skip_blanks();
+        if self.bump() != Some('=') {
+            return self.error(&format!("expected '=' after '{}'", key));
+        }
+        self.skip_blanks();
+
+        let value = match self.peek() {
+            Some('\'') => self.read_single_quoted()?,
+            Some('"') => self.read_double_quoted()?,
+            _ => return Ok(Some((key, self.read_unquoted()))),
+        };
+
+        // Only whitespace or a comment may follow a closing quote.
+        self.skip_blanks();
+        match self.peek() {
+            None | Some('\n') | Some('\r') => self.skip_line(),
+            Some('#') => self.skip_line(),
+            Some(_) => return self.error("unexpected characters after closing quote"),
+        }
+        Ok(Some((key, value)))
+    }
+
+    fn read_key(&mut self) -> String {
+        let mut key = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' {
+                key.push(c);
+                self.bump();
+            } else {
+                break;
+            }
+        }
+        key
+    }
+
+    fn read_unquoted(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\n' {
+                break;
+            }
+            if c == '#' && (value.is_empty() || value.ends_with([' ', '\t'])) {
+                self.skip_line();
+                return value.trim().to_owned();
+            }
+            value.push(c);
+            self.bump();
+        }
+        self.bump();
+        value.trim().to_owned()
+    }
+
+    fn read_single_quoted(&mut self) -> anyhow::Result<String> {
+        let start = self.line;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => anyhow::bail!("line {}: unterminated single-quoted value", start),
+                Some('\'') => return Ok(value),
+                Some(c) => value.push(c),
+            }
+        }
+    }
+
+    fn read_double_quoted(&mut self) -> anyhow::Result<String> {
+        let start = self.line;
+        self.bump();
+        let mut value = String::new();
+        loop {
+            match self.bump() {
+                None => anyhow::bail!("line {}: unterminated double-quoted value", start),
+                Some('"') => return Ok(value),
+                Some('\\') => match self.bump() {
+                    Some('n') => value.push('\n'),
+                    Some('r') => value.push('\r'),
+                    Some('t') => value.push('\t'),
+                    Some(c @ '"') | Some(c @ '\\') | Some(c @ '$') => value.push(c),
+                    Some(c) => {
+                        value.push('\\');
+                        value.push(c);
+                    }
+                    None => anyhow::bail!("line {}: unterminated double-quoted value", start),
+                },
+                Some(c) => value.push(c),
+            }
+        }
+    }
+}
+
+/// Writes key/value pairs in dotenv syntax that `parse` reads back unchanged.
+/// Values are double-quoted (and escaped) only if they need to be.
+pub(crate) fn to_string(entries: &[(String, String)]) -> String {
+    let mut text = String::new();
+    for (key, value) in entries {
+        text.push_str(key);
+        text.push('=');
+        text.push_str(&quote(value));
+        text.push('\n');
+    }
+    text
+}
+
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+%=".contains(c));
+    if plain {
+        return value.to_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Turns `(key, value)` literals into the owned pairs that `parse` returns.
+#[cfg(test)]
+pub(crate) fn pairs(entries: &[(&str, &str)]) -> Vec<(String, String)> {
+    entries
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unquoted_values_and_comments() {
+        let text = "# a comment\n\nA=1\nB = two words  \nC=x#not-a-comment\nD=y # a comment\nE=\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            pairs(&[
+                ("A", "1"),
+                ("B", "two words"),
+                ("C", "x#not-a-comment"),
+                ("D", "y"),
+                ("E", "")
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_export_prefix() {
+        assert_eq!(
+            parse("export A=1\nexport=2\n").unwrap(),
+            pairs(&[("A", "1"), ("export", "2")])
+        );
+    }
+
+    #[test]
+    fn parses_quoted_values() {
+        let text = "B='a \\n b $X'\nC=\"a\\nb\\t\\\"c\\\" \\\\ \\$X \\q\"\nD=\"multi\nline\" # comment\nE=1\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            pairs(&[
+                ("B", "a \\n b $X"),
+                ("C", "a\nb\t\"c\" \\ $X \\q"),
+                ("D", "multi\nline"),
+                ("E", "1")
+            ])
+        );
+    }
+
+    #[test]
+    fn parses_crlf_line_endings() {
+        assert_eq!(
+            parse("A=1\r\nB=\"2\"\r\n\r\nC='3'\r\n").unwrap(),
+            pairs(&[("A", "1"), ("B", "2"), ("C", "3")])
+        );
+    }
+
+    #[test]
+    fn keeps_duplicate_keys_in_file_order() {
+        assert_eq!(
+            parse("A=1\nA=2\n").unwrap(),
+            pairs(&[("A", "1"), ("A", "2")])
+        );
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let error = |text: &str| parse(text).unwrap_err().to_string();
+        assert_eq!(error("A=1\n=2\n"), "line 2: expected a variable name");
+        assert_eq!(error("1A=2"), "line 1: '1A' is not a valid variable name");
+        assert_eq!(error("A 1"), "line 1: expected '=' after 'A'");
+        assert_eq!(
+            error("A=1\nB=\"x\ny"),
+            "line 2: unterminated double-quoted value"
+        );
+        assert_eq!(error("A='x"), "line 1: unterminated single-quoted value");
+        assert_eq!(
+            error("A='it''s'"),
+            "line 1: unexpected characters after closing quote"
+        );
+        assert_eq!(
+            error("A=\"x\" y"),
+            "line 1: unexpected characters after closing quote"
+        );
+    }
+
+    #[test]
+    fn to_string_quotes_only_when_needed() {
+        assert_eq!(
+            to_string(&pairs(&[("A", "plain-value:1"), ("B", ""), ("C", "a b")])),
+            "A=plain-value:1\nB=\"\"\nC=\"a b\"\n"
+        );
+    }
+
+    #[test]
+    fn to_string_round_trips_through_parse() {
+        let entries = pairs(&[
+            ("PLAIN", "abc"),
+            ("EMPTY", ""),
+            ("SPACES", "  padded  "),
+            ("QUOTES", "it's \"quoted\""),
+            ("ESCAPES", "back\\slash $HOME\ttab"),
+            ("LINES", "one\ntwo\r\nthree"),
+            ("HASH", "a #b"),
+            ("UNICODE", "grüße ✓"),
+        ]);
+        assert_eq!(parse(&to_string(&entries)).unwrap(), entries);
+    }
+}
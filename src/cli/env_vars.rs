@@ -0,0 +1,776 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use anyhow::Context;
+use futures::{stream, StreamExt};
+use glob::{MatchOptions, Pattern};
+use hippo_openapi::models::{ChannelDto, EnvironmentVariableDto};
+use regex::Regex;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use super::commands::{environment_variable::ExportFormat, OutputFormat};
+use super::dotenv;
+use crate::client::Client;
+
+/// How many requests to have in flight at once when changing many variables.
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+/// What values of secret keys are shown as. Unlike `mask`, it gives away nothing
+/// about the value, as even a hash can be enough to guess a short secret.
+const SECRET_MASK: &str = "[secret]";
+
+/// Matches the values that `mask` produces, now and in earlier versions, and
+/// `SECRET_MASK`.
+const MASKED_VALUE_PATTERN: &str = r"^(sha256:[0-9a-f]{8}( \(\d+ chars\))?|\[secret\])$";
+
+/// Lists the environment variables bound to a channel.
+pub(crate) async fn list_for_channel(
+    client: &Client,
+    channel_id: &str,
+) -> anyhow::Result<Vec<EnvironmentVariableDto>> {
+    Ok(client
+        .list_environmentvariables()
+        .await?
+        .environment_variables
+        .into_iter()
+        .filter(|e| e.channel_id == channel_id)
+        .collect())
+}
+
+/// Reads an environment variable value from the source chosen on the command line,
+/// if any. Values from files and stdin lose a single trailing newline, which editors
+/// and `echo` add but which is almost never part of a secret.
+pub(crate) fn read_value(
+    from_file: Option<&Path>,
+    from_stdin: bool,
+    from_env: Option<&str>,
+) -> anyhow::Result<Option<String>> {
+    let value = if let Some(path) = from_file {
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?
+    } else if from_stdin {
+        let mut value = String::new();
+        std::io::stdin()
+            .read_to_string(&mut value)
+            .context("failed to read the value from stdin")?;
+        value
+    } else if let Some(name) = from_env {
+        return std::env::var(name)
+            .map(Some)
+            .with_context(|| format!("environment variable {} is not set", name));
+    } else {
+        return Ok(None);
+    };
+
+    let trimmed = value
+        .strip_suffix("\r\n")
+        .or_else(|| value.strip_suffix('\n'))
+        .unwrap_or(&value);
+    Ok(Some(trimmed.to_owned()))
+}
+
+/// Renders environment variables in the given format, sorted by key so that
+/// exports of different channels can be diffed. Values are written as they are, so
+/// leave out anything that shouldn't end up in the output.
+pub(crate) fn export(
+    vars: &[&EnvironmentVariableDto],
+    format: ExportFormat,
+) -> anyhow::Result<String> {
+    let sorted: BTreeMap<&str, &str> = vars
+        .iter()
+        .map(|e| (e.key.as_str(), e.value.as_str()))
+        .collect();
+    let text = match format {
+        ExportFormat::Dotenv => {
+            let entries: Vec<(String, String)> = sorted
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect();
+            dotenv::to_string(&entries)
+        }
+        ExportFormat::Json => format!("{}\n", serde_json::to_string_pretty(&sorted)?),
+        ExportFormat::Shell => {
+            if let Some(key) = sorted.keys().find(|k| !is_shell_name(k)) {
+                anyhow::bail!("'{}' is not a valid shell variable name", key);
+            }
+            sorted
+                .iter()
+                .map(|(k, v)| format!("export {}='{}'\n", k, v.replace('\'', "'\\''")))
+                .collect()
+        }
+    };
+    Ok(text)
+}
+
+/// Whether a key can be a shell variable name. Keys can also contain `.` and `-`,
+/// which shells don't allow.
+pub(crate) fn is_shell_name(key: &str) -> bool {
+    !key.starts_with(|c: char| c.is_ascii_digit())
+        && !key.is_empty()
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Fails if any of the values read from a file look like masked values, as written
+/// by older versions of `env export`, so that a mask is never uploaded over the
+/// real value.
+pub(crate) fn check_not_masked(entries: &[(String, String)]) -> anyhow::Result<()> {
+    let mask = Regex::new(MASKED_VALUE_PATTERN).unwrap();
+    let masked: Vec<&str> = entries
+        .iter()
+        .filter(|(_, value)| mask.is_match(value))
+        .map(|(key, _)| key.as_str())
+        .collect();
+    if !masked.is_empty() {
+        anyhow::bail!(
+            "the values of {} are masked rather than real values; set them in the file, or leave them out",
+            masked.join(", ")
+        );
+    }
+    Ok(())
+}
+
+#[derive(Serialize)]
+pub(crate) struct CreatedVariable {
+    pub key: String,
+    pub id: String,
+}
+
+#[derive(Serialize)]
+pub(crate) struct FailedVariable {
+    pub key: String,
+    pub error: String,
+}
+
+/// The outcome of `hippo env import`.
+#[derive(Default, Serialize)]
+pub(crate) struct ImportReport {
+    pub created: Vec<CreatedVariable>,
+    pub skipped: Vec<String>,
+    pub failed: Vec<FailedVariable>,
+}
+
+/// Creates each of `entries` on the channel, skipping keys the channel already has.
+/// If a key appears more than once, the last value wins, as it would if the file
+/// were sourced by a shell.
+pub(crate) async fn import(
+    client: &Client,
+    channel: &ChannelDto,
+    entries: Vec<(String, String)>,
+) -> anyhow::Result<ImportReport> {
+    let existing: HashSet<String> = list_for_channel(client, &channel.id)
+        .await?
+        .into_iter()
+        .map(|e| e.key)
+        .collect();
+
+    let mut report = ImportReport::default();
+    let mut to_create: Vec<(String, String)> = vec![];
+    for (key, value) in entries {
+        if existing.contains(&key) {
+            if !report.skipped.contains(&key) {
+                report.skipped.push(key);
+            }
+        } else if let Some(entry) = to_create.iter_mut().find(|(k, _)| *k == key) {
+            entry.1 = value;
+        } else {
+            to_create.push((key, value));
+        }
+    }
+
+    let results: Vec<_> = stream::iter(to_create)
+        .map(|(key, value)| async move {
+            let result = client
+                .add_environment_variable(key.clone(), value, channel.id.clone())
+                .await;
+            (key, result)
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .collect()
+        .await;
+
+    for (key, result) in results {
+        match result {
+            Ok(id) => report.created.push(CreatedVariable { key, id }),
+            Err(e) => report.failed.push(FailedVariable {
+                key,
+                error: e.to_string(),
+            }),
+        }
+    }
+    Ok(report)
+}
+
+impl ImportReport {
+    pub(crate) fn print(&self, channel: &ChannelDto, output: OutputFormat) -> anyhow::Result<()> {
+        match output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+            OutputFormat::Text => {
+                for created in &self.created {
+                    println!("Created {} (ID = '{}')", created.key, created.id);
+                }
+                for key in &self.skipped {
+                    println!("Skipped {}: already set on channel {}", key, channel.name);
+                }
+                for failed in &self.failed {
+                    println!("Failed {}: {}", failed.key, failed.error);
+                }
+                println!(
+                    "Imported into channel {}: {} created, {} skipped, {} failed",
+                    channel.name,
+                    self.created.len(),
+                    self.skipped.len(),
+                    self.failed.len()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Masks a value for display. The short hash is enough to tell whether two values
+/// differ without revealing either of them.
+pub(crate) fn mask(value: &str) -> String {
+    let digest = Sha256::digest(value.as_bytes());
+    let prefix: String = digest
+        .iter()
+        .take(4)
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("sha256:{}", prefix)
+}
+
+/// Decides which environment variable values may be displayed. Values are masked
+/// unless revealing them was asked for, and values of keys matching one of the
+/// configured secret patterns are masked regardless.
+pub(crate) struct Masker {
+    reveal: bool,
+    secret_keys: Vec<Pattern>,
+}
+
+impl Masker {
+    pub(crate) fn new(reveal: bool, secret_keys: &[String]) -> anyhow::Result<Self> {
+        let secret_keys = secret_keys
+            .iter()
+            .map(|p| Pattern::new(p).with_context(|| format!("invalid secret key pattern '{}'", p)))
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            reveal,
+            secret_keys,
+        })
+    }
+
+    pub(crate) fn is_secret(&self, key: &str) -> bool {
+        let options = MatchOptions {
+            case_sensitive: false,
+            ..Default::default()
+        };
+        self.secret_keys
+            .iter()
+            .any(|p| p.matches_with(key, options))
+    }
+
+    pub(crate) fn show(&self, key: &str, value: &str) -> String {
+        if self.is_secret(key) {
+            SECRET_MASK.to_owned()
+        } else if self.reveal {
+            value.to_owned()
+        } else {
+            mask(value)
+        }
+    }
+}
+
+/// A change that `env sync` makes to bring a channel in line with a file.
+pub(crate) enum Change {
+    Add {
+        key: String,
+        value: String,
+    },
+    Update {
+        id: String,
+        key: String,
+        old: String,
+        new: String,
+    },
+    Remove {
+        id: String,
+        key: String,
+        value: String,
+    },
+}
+
+impl Change {
+    fn key(&self) -> &str {
+        match self {
+            Self::Add { key, .. } | Self::Update { key, .. } | Self::Remove { key, .. } => key,
+        }
+    }
+
+    fn action(&self) -> &'static str {
+        match self {
+            Self::Add { .. } => "add",
+            Self::Update { .. } => "update",
+            Self::Remove { .. } => "remove",
+        }
+    }
+
+    pub(crate) fn describe(&self, masker: &Masker) -> String {
+        match self {
+            Self::Add { key, value } => format!("+ {} = {}", key, masker.show(key, value)),
+            Self::Update { key, old, new, .. } => format!(
+                "~ {} = {} -> {}",
+                key,
+                masker.show(key, old),
+                masker.show(key, new)
+            ),
+            Self::Remove { key, value, .. } => format!("- {} = {}", key, masker.show(key, value)),
+        }
+    }
+
+    /// The API request that applying this change sends, for `--dry-run`.
+    fn request(&self, channel_id: &str, masker: &Masker) -> String {
+        match self {
+            Self::Add { key, value } => format!(
+                "POST /api/environmentvariable {}",
+                serde_json::json!({ "key": key, "value": masker.show(key, value), "channelId": channel_id })
+            ),
+            Self::Update { id, key, new, .. } => format!(
+                "PUT /api/environmentvariable/{} {}",
+                id,
+                serde_json::json!({ "id": id, "key": key, "value": masker.show(key, new) })
+            ),
+            Self::Remove { id, .. } => format!("DELETE /api/environmentvariable/{}", id),
+        }
+    }
+
+    async fn apply(&self, client: &Client, channel_id: &str) -> anyhow::Result<()> {
+        match self {
+            Self::Add { key, value } => client
+                .add_environment_variable(key.clone(), value.clone(), channel_id.to_owned())
+                .await
+                .map(|_| ()),
+            Self::Update { id, key, new, .. } => {
+                client
+                    .update_environment_variable(id.clone(), key.clone(), new.clone())
+                    .await
+            }
+            Self::Remove { id, .. } => client.remove_environment_variable(id.clone()).await,
+        }
+    }
+}
+
+/// The changes `env sync` or `env copy` needs to make to a channel's variables.
+pub(crate) struct Plan {
+    pub changes: Vec<Change>,
+    pub unchanged: usize,
+    /// Keys that are left as they are on the channel even though they differ from the
+    /// source, and why.
+    pub kept: Vec<String>,
+    pub kept_reason: &'static str,
+}
+
+/// Works out the changes that make `current` match `desired`. As with `import`, if a
+/// key appears in `desired` more than once the last value wins.
+pub(crate) fn plan_sync(
+    current: Vec<EnvironmentVariableDto>,
+    desired: Vec<(String, String)>,
+    prune: bool,
+) -> Plan {
+    let wanted: HashMap<String, String> = desired.into_iter().collect();
+    let mut plan = Plan {
+        changes: vec![],
+        unchanged: 0,
+        kept: vec![],
+        kept_reason: "not in the file (pass --prune to remove them)",
+    };
+
+    let mut matched = HashSet::new();
+    for var in current {
+        match wanted.get(&var.key) {
+            // The first variable with a wanted key is brought up to date; any others
+            // with the same key are surplus, like keys missing from the file.
+            Some(value) if matched.insert(var.key.clone()) => {
+                if *value == var.value {
+                    plan.unchanged += 1;
+                } else {
+                    plan.changes.push(Change::Update {
+                        id: var.id,
+                        key: var.key,
+                        old: var.value,
+                        new: value.clone(),
+                    });
+                }
+            }
+            _ if prune => plan.changes.push(Change::Remove {
+                id: var.id,
+                key: var.key,
+                value: var.value,
+            }),
+            _ => plan.kept.push(var.key),
+        }
+    }
+    for (key, value) in wanted {
+        if !matched.contains(&key) {
+            plan.changes.push(Change::Add { key, value });
+        }
+    }
+
+    plan.changes.sort_by(|a, b| a.key().cmp(b.key()));
+    plan.kept.sort();
+    plan
+}
+
+/// Selects environment variables by key, with `--only` and `--exclude` patterns.
+pub(crate) struct KeyFilter {
+    only: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl KeyFilter {
+    pub(crate) fn new(only: &[String], exclude: &[String]) -> anyhow::Result<Self> {
+        let compile = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|p| Pattern::new(p).with_context(|| format!("invalid key pattern '{}'", p)))
+                .collect::<anyhow::Result<Vec<_>>>()
+        };
+        Ok(Self {
+            only: compile(only)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    /// Whether a key is selected: it matches one of the `only` patterns (if there are
+    /// any) and none of the `exclude` patterns.
+    pub(crate) fn matches(&self, key: &str) -> bool {
+        (self.only.is_empty() || self.only.iter().any(|p| p.matches(key)))
+            && !self.exclude.iter().any(|p| p.matches(key))
+    }
+}
+
+/// Works out the changes that copy the selected `source` variables to a channel that
+/// currently has `target`. Keys the target already has with a different value are
+/// only replaced if `overwrite` is set.
+pub(crate) fn plan_copy(
+    source: Vec<EnvironmentVariableDto>,
+    target: Vec<EnvironmentVariableDto>,
+    filter: &KeyFilter,
+    overwrite: bool,
+) -> Plan {
+    let mut plan = Plan {
+        changes: vec![],
+        unchanged: 0,
+        kept: vec![],
+        kept_reason: "already set on the target channel (pass --overwrite to replace them)",
+    };
+
+    let mut seen = HashSet::new();
+    for var in source {
+        if !filter.matches(&var.key) || !seen.insert(var.key.clone()) {
+            continue;
+        }
+        match target.iter().find(|t| t.key == var.key) {
+            None => plan.changes.push(Change::Add {
+                key: var.key,
+                value: var.value,
+            }),
+            Some(existing) if existing.value == var.value => plan.unchanged += 1,
+            Some(existing) if overwrite => plan.changes.push(Change::Update {
+                id: existing.id.clone(),
+                key: var.key,
+                old: existing.value.clone(),
+                new: var.value,
+            }),
+            Some(_) => plan.kept.push(var.key),
+        }
+    }
+
+    plan.changes.sort_by(|a, b| a.key().cmp(b.key()));
+    plan.kept.sort();
+    plan
+}
+
+/// Applies the changes, returning the errors of those that failed, by index.
+pub(crate) async fn apply(
+    client: &Client,
+    channel_id: &str,
+    changes: &[Change],
+) -> HashMap<usize, String> {
+    stream::iter(changes.iter().enumerate())
+        .map(|(index, change)| async move { (index, change.apply(client, channel_id).await) })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .filter_map(|(index, result)| async move { result.err().map(|e| (index, e.to_string())) })
+        .collect()
+        .await
+}
+
+#[derive(Serialize)]
+struct ChangeReport<'a> {
+    action: &'static str,
+    key: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    old: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    new: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct PlanReport<'a> {
+    channel: &'a str,
+    dry_run: bool,
+    changes: Vec<ChangeReport<'a>>,
+    unchanged: usize,
+    kept: &'a [String],
+}
+
+impl Plan {
+    /// Prints the changes, with values masked. For a dry run, also prints the requests
+    /// that would be sent; otherwise `failures` says which changes could not be made.
+    pub(crate) fn print(
+        &self,
+        channel: &ChannelDto,
+        output: OutputFormat,
+        dry_run: bool,
+        failures: &HashMap<usize, String>,
+        masker: &Masker,
+    ) -> anyhow::Result<()> {
+        match output {
+            OutputFormat::Json => {
+                let changes = self
+                    .changes
+                    .iter()
+                    .enumerate()
+                    .map(|(index, change)| {
+                        let (old, new) = match change {
+                            Change::Add { key, value } => (None, Some(masker.show(key, value))),
+                            Change::Update { key, old, new, .. } => {
+                                (Some(masker.show(key, old)), Some(masker.show(key, new)))
+                            }
+                            Change::Remove { key, value, .. } => {
+                                (Some(masker.show(key, value)), None)
+                            }
+                        };
+                        ChangeReport {
+                            action: change.action(),
+                            key: change.key(),
+                            old,
+                            new,
+                            error: failures.get(&index).map(|e| e.as_str()),
+                        }
+                    })
+                    .collect();
+                let report = PlanReport {
+                    channel: &channel.name,
+                    dry_run,
+                    changes,
+                    unchanged: self.unchanged,
+                    kept: &self.kept,
+                };
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+            OutputFormat::Text => {
+                for (index, change) in self.changes.iter().enumerate() {
+                    match failures.get(&index) {
+                        Some(error) => println!("{} (FAILED: {})", change.describe(masker), error),
+                        None => println!("{}", change.describe(masker)),
+                    }
+                }
+                if !self.kept.is_empty() {
+                    println!(
+                        "Kept {} variable(s) {}: {}",
+                        self.kept.len(),
+                        self.kept_reason,
+                        self.kept.join(", ")
+                    );
+                }
+                if dry_run {
+                    println!("Dry run: no changes made. The following requests would be sent:");
+                    for change in &self.changes {
+                        println!("  {}", change.request(&channel.id, masker));
+                    }
+                } else {
+                    println!(
+                        "Updated channel {}: {} changed, {} unchanged, {} failed",
+                        channel.name,
+                        self.changes.len() - failures.len(),
+                        self.unchanged,
+                        failures.len()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::dotenv::pairs;
+    use super::*;
+
+    fn var(id: &str, key: &str, value: &str) -> EnvironmentVariableDto {
+        EnvironmentVariableDto {
+            id: id.to_owned(),
+            key: key.to_owned(),
+            value: value.to_owned(),
+            channel_id: "channel".to_owned(),
+        }
+    }
+
+    fn summary(plan: &Plan) -> Vec<String> {
+        plan.changes
+            .iter()
+            .map(|change| match change {
+                Change::Add { key, value } => format!("add {}={}", key, value),
+                Change::Update { id, key, old, new } => {
+                    format!("update {} {}: {} -> {}", id, key, old, new)
+                }
+                Change::Remove { id, key, value } => format!("remove {} {}={}", id, key, value),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn export_sorts_by_key_and_quotes_for_each_format() {
+        let b = var("1", "B", "it's");
+        let a = var("2", "A", "x y");
+        let vars = [&b, &a];
+        assert_eq!(
+            export(&vars, ExportFormat::Dotenv).unwrap(),
+            "A=\"x y\"\nB=\"it's\"\n"
+        );
+        assert_eq!(
+            export(&vars, ExportFormat::Shell).unwrap(),
+            "export A='x y'\nexport B='it'\\''s'\n"
+        );
+        assert_eq!(
+            export(&vars, ExportFormat::Json).unwrap(),
+            "{\n  \"A\": \"x y\",\n  \"B\": \"it's\"\n}\n"
+        );
+    }
+
+    #[test]
+    fn export_refuses_keys_a_shell_cannot_set() {
+        let dotted = var("1", "LOG.LEVEL", "debug");
+        let dashed = var("2", "my-key", "x");
+        let plain = var("3", "_PORT2", "80");
+        assert!(export(&[&dotted], ExportFormat::Shell).is_err());
+        assert!(export(&[&dashed], ExportFormat::Shell).is_err());
+        assert!(export(&[&dotted, &dashed], ExportFormat::Dotenv).is_ok());
+        assert_eq!(
+            export(&[&plain], ExportFormat::Shell).unwrap(),
+            "export _PORT2='80'\n"
+        );
+        assert!(!is_shell_name("2FA"));
+        assert!(!is_shell_name(""));
+    }
+
+    #[test]
+    fn check_not_masked_rejects_masked_values() {
+        let masked = mask("hunter2");
+        assert!(check_not_masked(&pairs(&[("A", "1"), ("B", &masked)])).is_err());
+        assert!(check_not_masked(&pairs(&[("B", "sha256:6b86b273 (1 chars)")])).is_err());
+        assert!(check_not_masked(&pairs(&[("B", SECRET_MASK)])).is_err());
+        assert!(check_not_masked(&pairs(&[("A", "sha256:not-a-mask"), ("C", "")])).is_ok());
+    }
+
+    #[test]
+    fn masker_shows_nothing_about_secret_values() {
+        let masker = Masker::new(true, &["*_token".to_owned()]).unwrap();
+        assert_eq!(masker.show("API_TOKEN", "hunter2"), SECRET_MASK);
+        assert_eq!(masker.show("HOST", "example.com"), "example.com");
+
+        let masker = Masker::new(false, &["*_TOKEN".to_owned()]).unwrap();
+        assert_eq!(masker.show("API_TOKEN", "hunter2"), SECRET_MASK);
+        assert_eq!(masker.show("HOST", "1"), "sha256:6b86b273");
+    }
+
+    #[test]
+    fn plan_sync_adds_updates_and_keeps_without_prune() {
+        let current = vec![var("1", "A", "1"), var("2", "B", "old"), var("3", "C", "x")];
+        let plan = plan_sync(
+            current,
+            pairs(&[("B", "new"), ("A", "1"), ("D", "4")]),
+            false,
+        );
+        assert_eq!(summary(&plan), ["update 2 B: old -> new", "add D=4"]);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.kept, ["C"]);
+    }
+
+    #[test]
+    fn plan_sync_removes_missing_keys_with_prune() {
+        let current = vec![var("1", "A", "1"), var("2", "Z", "z"), var("3", "C", "x")];
+        let plan = plan_sync(current, pairs(&[("A", "1")]), true);
+        assert_eq!(summary(&plan), ["remove 3 C=x", "remove 2 Z=z"]);
+        assert_eq!(plan.unchanged, 1);
+        assert!(plan.kept.is_empty());
+    }
+
+    #[test]
+    fn plan_sync_uses_the_last_value_of_a_duplicate_key_in_the_file() {
+        let plan = plan_sync(vec![], pairs(&[("A", "1"), ("A", "2")]), false);
+        assert_eq!(summary(&plan), ["add A=2"]);
+    }
+
+    #[test]
+    fn plan_sync_treats_duplicate_keys_on_the_channel_as_surplus() {
+        let current = vec![var("1", "A", "1"), var("2", "A", "2")];
+        let plan = plan_sync(current.clone(), pairs(&[("A", "1")]), false);
+        assert!(plan.changes.is_empty());
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.kept, ["A"]);
+
+        let plan = plan_sync(current, pairs(&[("A", "1")]), true);
+        assert_eq!(summary(&plan), ["remove 2 A=2"]);
+    }
+
+    #[test]
+    fn key_filter_applies_only_then_exclude() {
+        let all = KeyFilter::new(&[], &[]).unwrap();
+        assert!(all.matches("ANYTHING"));
+
+        let filter = KeyFilter::new(&["DB_*".to_owned()], &["*_PASSWORD".to_owned()]).unwrap();
+        assert!(filter.matches("DB_HOST"));
+        assert!(!filter.matches("DB_PASSWORD"));
+        assert!(!filter.matches("API_HOST"));
+
+        assert!(KeyFilter::new(&["[".to_owned()], &[]).is_err());
+    }
+
+    #[test]
+    fn plan_copy_adds_missing_keys_and_keeps_differing_ones() {
+        let source = vec![var("1", "A", "1"), var("2", "B", "new"), var("3", "C", "3")];
+        let target = vec![var("t1", "A", "1"), var("t2", "B", "old")];
+        let plan = plan_copy(source, target, &KeyFilter::new(&[], &[]).unwrap(), false);
+        assert_eq!(summary(&plan), ["add C=3"]);
+        assert_eq!(plan.unchanged, 1);
+        assert_eq!(plan.kept, ["B"]);
+    }
+
+    #[test]
+    fn plan_copy_replaces_differing_keys_with_overwrite() {
+        let source = vec![var("1", "B", "new")];
+        let target = vec![var("t2", "B", "old"), var("t3", "EXTRA", "x")];
+        let plan = plan_copy(source, target, &KeyFilter::new(&[], &[]).unwrap(), true);
+        assert_eq!(summary(&plan), ["update t2 B: old -> new"]);
+        assert!(plan.kept.is_empty());
+    }
+
+    #[test]
+    fn plan_copy_skips_filtered_and_repeated_source_keys() {
+        let source = vec![
+            var("1", "DB_HOST", "db"),
+            var("2", "DB_HOST", "other"),
+            var("3", "DB_PASSWORD", "secret"),
+            var("4", "PORT", "80"),
+        ];
+        let filter = KeyFilter::new(&["DB_*".to_owned()], &["*_PASSWORD".to_owned()]).unwrap();
+        let plan = plan_copy(source, vec![], &filter, false);
+        assert_eq!(summary(&plan), ["add DB_HOST=db"]);
+    }
+}
@@ -0,0 +1,23 @@
+use std::{path::Path, process::Command};
+
+/// Runs git in the working directory and returns what it printed.
+pub(crate) fn run(args: &[&str]) -> anyhow::Result<String> {
+    run_in(Path::new("."), args)
+}
+
+/// Runs git in `dir` and returns what it printed.
+pub(crate) fn run_in(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow::anyhow!("failed to run git: {}", e))?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -1,24 +1,44 @@
+mod certificate_bundle;
+mod certificates;
+mod channels;
 mod commands;
+mod config;
+mod credential_helper;
+mod dotenv;
+mod env_vars;
+mod git;
+mod preview;
+mod probe;
+mod project;
+mod resolve;
+mod revision_number;
+mod wait;
+mod whoami;
 
 use commands::{
     app::Commands as AppCommands, certificate::Commands as CertificateCommands,
-    channel::Commands as ChannelCommands, environment_variable::Commands as EnvCommands,
-    revision::Commands as RevisionCommands, Commands,
+    channel::Commands as ChannelCommands,
+    config::{Commands as ConfigCommands, Key as ConfigKey},
+    environment_variable::{Commands as EnvCommands, ExportFormat},
+    preview::Commands as PreviewCommands,
+    revision::Commands as RevisionCommands,
+    Commands, OutputFormat,
 };
 
 use crate::client::{Client, ConnectionInfo};
+use certificates::{CertificateSummary, KeyPairPem};
+use config::HippoConfig;
+use credential_helper::CredentialHelper;
+use project::ProjectConfig;
+use whoami::{Session, SessionStatus};
 
-use clap::Parser;
-use dialoguer::{Input, Password};
+use anyhow::Context;
+use clap::{ArgEnum, Parser};
+use dialoguer::{Confirm, Input, Password};
 use dirs::config_dir;
-use hippo_openapi::models::{ChannelRevisionSelectionStrategy, TokenInfo};
+use hippo_openapi::models::ChannelRevisionSelectionStrategy;
 use log::LevelFilter;
-use serde::{Deserialize, Serialize};
-use std::{
-    fs::{self, File},
-    io::BufReader,
-    path::PathBuf,
-};
+use std::{fs, io::IsTerminal, path::PathBuf};
 
 const ABOUT_HIPPO: &str = r#"Create and manage Hippo applications.
 
@@ -30,25 +50,6 @@ Many 'hippo' commands operate on a 'HIPPOFACTS' TOML file located in the same di
 in which you are running the 'hippo' command.
 "#;
 
-#[derive(Serialize, Deserialize)]
-struct HippoConfig {
-    danger_accept_invalid_certs: bool,
-    token_info: Option<TokenInfo>,
-    username: String,
-    url: String,
-}
-
-impl Default for HippoConfig {
-    fn default() -> Self {
-        Self {
-            danger_accept_invalid_certs: false,
-            token_info: None,
-            username: "".to_owned(),
-            url: "http://localhost:5309".to_owned(),
-        }
-    }
-}
-
 /// The Hippo commandline client
 #[derive(Parser)]
 #[clap(name = "hippo")]
@@ -58,6 +59,15 @@ pub struct Cli {
     #[clap(short, long, parse(from_os_str), value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// Uses the named login profile instead of the default one (ignored if --config
+    /// is given)
+    #[clap(env = "HIPPO_PROFILE", short, long)]
+    profile: Option<String>,
+
+    /// The format for command output
+    #[clap(short, long, arg_enum, global = true)]
+    output: Option<OutputFormat>,
+
     /// Turn debugging information on
     #[clap(short, long, parse(from_occurrences))]
     verbose: usize,
@@ -68,23 +78,34 @@ pub struct Cli {
 
 impl Cli {
     pub async fn execute(&self) -> anyhow::Result<()> {
-        let hippo_config_path = match &self.config {
-            Some(p) => p.clone(),
-            None => PathBuf::from(
-                config_dir()
-                    .map(|h| h.join("hippo").join("hippo.json"))
-                    .unwrap(),
-            ),
+        let project = ProjectConfig::discover()?;
+        let profile = self.profile.as_ref().or(project.profile.as_ref());
+
+        let hippo_config_path = match (&self.config, profile) {
+            (Some(p), _) => p.clone(),
+            (None, Some(profile)) => {
+                config::profile_path(&config_dir().unwrap().join("hippo"), profile)?
+            }
+            (None, None) => config_dir()
+                .map(|h| h.join("hippo").join("hippo.json"))
+                .unwrap(),
+        };
+        let profile_name = match (&self.config, profile) {
+            (Some(p), _) => p.display().to_string(),
+            (None, Some(profile)) => profile.clone(),
+            (None, None) => "default".to_owned(),
         };
 
-        // TODO: switch from std::fs to tokio::fs once serde_json implements tokio support
-        // https://github.com/serde-rs/json/issues/316
-        let mut hippo_conf: HippoConfig = Default::default();
-        if hippo_config_path.exists() {
-            let file = File::open(hippo_config_path.clone())?;
-            let reader = BufReader::new(file);
-            hippo_conf = serde_json::from_reader(reader)?;
-        }
+        let mut hippo_conf = HippoConfig::load(&hippo_config_path)?;
+        let output = self.output.or(hippo_conf.output).unwrap_or_default();
+        let default_app = project
+            .app
+            .clone()
+            .or_else(|| hippo_conf.default_app.clone());
+        let default_channel = project
+            .channel
+            .clone()
+            .or_else(|| hippo_conf.default_channel.clone());
 
         let mut builder = env_logger::builder();
         builder.parse_default_env();
@@ -99,10 +120,31 @@ impl Cli {
 
         builder.init();
 
+        // When a credential helper is configured the token lives with the helper rather
+        // than in the config file, so only ask for it if the command talks to the server.
+        let token_info = match &hippo_conf.credential_helper {
+            Some(program)
+                if !matches!(
+                    self.command,
+                    Commands::Config(_)
+                        | Commands::Login { .. }
+                        | Commands::Logout {}
+                        | Commands::Register { .. }
+                        | Commands::Revision(RevisionCommands::Version { .. })
+                ) =>
+            {
+                CredentialHelper::new(program)
+                    .get(&hippo_conf.url, &hippo_conf.username)
+                    .await?
+            }
+            Some(_) => None,
+            None => hippo_conf.token_info.clone(),
+        };
+
         let hippo_client = Client::new(ConnectionInfo {
-            url: hippo_conf.url,
+            url: hippo_conf.url.clone(),
             danger_accept_invalid_certs: hippo_conf.danger_accept_invalid_certs,
-            api_key: hippo_conf.token_info.map_or(None, |t| t.token),
+            api_key: token_info.as_ref().and_then(|t| t.token.clone()),
         });
 
         match &self.command {
@@ -128,10 +170,26 @@ impl Cli {
                 name,
                 public_key_path,
                 private_key_path,
+                passphrase_from_env,
             }) => {
-                // open files and read their contents
-                let public_key = fs::read_to_string(public_key_path)?;
-                let private_key = fs::read_to_string(private_key_path)?;
+                let passphrase =
+                    certificate_bundle::passphrase_from_env(passphrase_from_env.as_deref())?;
+                let KeyPairPem {
+                    public_key,
+                    private_key,
+                } = certificate_bundle::read(
+                    public_key_path,
+                    private_key_path.as_deref(),
+                    passphrase.as_deref(),
+                )?;
+                let info = certificates::validate(&public_key, &private_key)
+                    .context("certificate rejected")?;
+                println!(
+                    "Certificate for {} issued by {}, valid until {}",
+                    info.sans.join(", "),
+                    info.issuer,
+                    certificates::format_date(&info.not_after)
+                );
                 let id = hippo_client
                     .add_certificate(name.to_owned(), public_key, private_key)
                     .await?;
@@ -139,9 +197,170 @@ impl Cli {
                 println!("IMPORTANT: save this Certificate ID for later - you will need it to update and/or delete the Certificate");
             }
 
+            Commands::Certificate(CertificateCommands::Generate {
+                domains,
+                ca,
+                days,
+                out_dir,
+                upload,
+                name,
+                channel,
+                app,
+            }) => {
+                fs::create_dir_all(out_dir)?;
+                let authority = if *ca {
+                    let cert_path = out_dir.join(certificates::DEV_CA_CERT_FILE);
+                    let key_path = out_dir.join(certificates::DEV_CA_KEY_FILE);
+                    if cert_path.exists() {
+                        println!("Using development CA {}", cert_path.display());
+                        Some(KeyPairPem::load(&cert_path, &key_path)?)
+                    } else {
+                        let authority = certificates::generate_ca(3650)?;
+                        authority.save(&cert_path, &key_path)?;
+                        println!(
+                            "Created development CA {} - add it to your trusted certificates to trust the certificates it signs",
+                            cert_path.display()
+                        );
+                        Some(authority)
+                    }
+                } else {
+                    None
+                };
+
+                let generated = certificates::generate(domains, *days, authority.as_ref())?;
+                let stem = domains[0].replace('*', "_wildcard");
+                let cert_path = out_dir.join(format!("{}.crt", stem));
+                let key_path = out_dir.join(format!("{}.key", stem));
+                generated.save(&cert_path, &key_path)?;
+                println!(
+                    "Wrote certificate {} and private key {}",
+                    cert_path.display(),
+                    key_path.display()
+                );
+
+                if *upload || channel.is_some() {
+                    let name = name.clone().unwrap_or_else(|| domains[0].clone());
+                    let id = hippo_client
+                        .add_certificate(name.clone(), generated.public_key, generated.private_key)
+                        .await?;
+                    println!("Added Certificate {} (ID = '{}')", name, id);
+                    if let Some(channel) = channel {
+                        let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                        let channel = resolve::channel(&hippo_client, channel, app).await?;
+                        let mut command = channels::update_command(&channel);
+                        command.certificate_id = Some(id);
+                        hippo_client.update_channel(command).await?;
+                        println!("Channel {} now uses certificate {}", channel.name, name);
+                    }
+                }
+            }
+
             Commands::Certificate(CertificateCommands::List { }) => {
                 let certificates = hippo_client.list_certificates().await?;
-                println!("{}", serde_json::to_string_pretty(&certificates.certificates)?);
+                let summaries: Vec<_> = certificates
+                    .certificates
+                    .iter()
+                    .map(CertificateSummary::new)
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&summaries)?);
+            }
+
+            Commands::Certificate(CertificateCommands::Check { within }) => {
+                let period = chrono::Duration::from_std(humantime::parse_duration(within)?)
+                    .context("the --within period is too long")?;
+                let now = chrono::Utc::now();
+                let certificates = hippo_client.list_certificates().await?;
+                let offenders: Vec<_> = certificates
+                    .certificates
+                    .iter()
+                    .map(CertificateSummary::new)
+                    .filter(|c| c.expires_within(now, period))
+                    .collect();
+                match output {
+                    OutputFormat::Json => {
+                        println!("{}", serde_json::to_string_pretty(&offenders)?)
+                    }
+                    OutputFormat::Text => {
+                        for certificate in &offenders {
+                            print!(
+                                "Certificate {} (ID = '{}') {}",
+                                certificate.name,
+                                certificate.id,
+                                certificate.describe_expiry(now)
+                            );
+                            if certificate.channels.is_empty() {
+                                println!();
+                            } else {
+                                println!(", used by {}", certificate.channels.join(", "));
+                            }
+                        }
+                        if offenders.is_empty() {
+                            println!(
+                                "All {} certificate(s) are valid for at least {}",
+                                certificates.certificates.len(),
+                                within
+                            );
+                        }
+                    }
+                }
+                if !offenders.is_empty() {
+                    anyhow::bail!(
+                        "{} certificate(s) expired or expiring within {}",
+                        offenders.len(),
+                        within
+                    );
+                }
+            }
+
+            Commands::Certificate(CertificateCommands::Rotate {
+                old,
+                public_key,
+                private_key,
+                passphrase_from_env,
+                name,
+                remove_old,
+            }) => {
+                let old = resolve::certificate(&hippo_client, old).await?;
+                let passphrase =
+                    certificate_bundle::passphrase_from_env(passphrase_from_env.as_deref())?;
+                let KeyPairPem {
+                    public_key,
+                    private_key,
+                } = certificate_bundle::read(
+                    public_key,
+                    private_key.as_deref(),
+                    passphrase.as_deref(),
+                )?;
+                let info = certificates::validate(&public_key, &private_key)
+                    .context("certificate rejected")?;
+                if let Ok(old_info) = certificates::inspect(&old.public_key) {
+                    let dropped: Vec<_> = old_info
+                        .sans
+                        .iter()
+                        .filter(|san| !info.sans.contains(san))
+                        .map(|san| san.as_str())
+                        .collect();
+                    if !dropped.is_empty() {
+                        eprintln!(
+                            "Warning: the new certificate does not cover {}, which the old one did",
+                            dropped.join(", ")
+                        );
+                    }
+                }
+                let name = name.clone().unwrap_or_else(|| old.name.clone());
+                certificates::rotate(&hippo_client, &old, name, public_key, private_key).await?;
+                if *remove_old {
+                    hippo_client
+                        .remove_certificate(old.id.clone())
+                        .await
+                        .with_context(|| {
+                            format!(
+                                "the channels now use the new certificate, but the old certificate {} could not be removed",
+                                old.id
+                            )
+                        })?;
+                    println!("Removed Certificate {}", old.id);
+                }
             }
 
             Commands::Certificate(CertificateCommands::Remove { id }) => {
@@ -165,13 +384,32 @@ impl Cli {
                     (None, Some(_)) => ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
                     _ => ChannelRevisionSelectionStrategy::UseRangeRule,
                 };
+                let range_rule = match (range_rule, revision_id) {
+                    (None, None) => Some(channels::DEFAULT_RANGE_RULE.to_owned()),
+                    _ => range_rule.clone(),
+                };
+                // Check the rule before contacting the server, so a mistake in it is
+                // reported as such rather than as whatever the server says.
+                let req = range_rule
+                    .as_deref()
+                    .map(channels::parse_range_rule)
+                    .transpose()?;
+                let app = resolve::app_or_default(
+                    &hippo_client,
+                    app_id.as_deref(),
+                    default_app.as_deref(),
+                )
+                .await?;
+                if let (Some(rule), Some(req)) = (&range_rule, &req) {
+                    channels::warn_if_unmatched(&hippo_client, &app.id, rule, req).await?;
+                }
                 let id = hippo_client
                     .add_channel(
-                        app_id.to_owned(),
+                        app.id,
                         name.to_owned(),
                         domain.to_owned(),
                         revision_selection_strategy,
-                        range_rule.to_owned(),
+                        range_rule,
                         revision_id.to_owned(),
                         certificate_id.to_owned(),
                     )
@@ -181,33 +419,574 @@ impl Cli {
             }
 
             Commands::Channel(ChannelCommands::List { }) => {
-                let channels = hippo_client.list_channels().await?;
+                let masker = env_vars::Masker::new(false, &hippo_conf.secret_keys)?;
+                let mut channels = hippo_client.list_channels().await?;
+                for env in channels
+                    .channels
+                    .iter_mut()
+                    .flat_map(|c| c.environment_variables.iter_mut())
+                {
+                    env.value = masker.show(&env.key, &env.value);
+                }
                 println!("{}", serde_json::to_string_pretty(&channels.channels)?);
             }
 
+            Commands::Channel(ChannelCommands::Pin {
+                channel,
+                revision,
+                app,
+            }) => {
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let channel = resolve::channel(&hippo_client, channel, app).await?;
+                let revision = resolve::revision(&hippo_client, &channel.app_id, revision).await?;
+                channels::pin(&hippo_client, &channel, &revision).await?;
+                println!(
+                    "Pinned channel {} to revision {} (was {})",
+                    channel.name,
+                    revision.revision_number,
+                    channels::describe_selection(&channel)
+                );
+            }
+
+            Commands::Channel(ChannelCommands::Unpin {
+                channel,
+                range_rule,
+                app,
+            }) => {
+                let given = range_rule
+                    .as_deref()
+                    .map(channels::parse_range_rule)
+                    .transpose()?;
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let channel = resolve::channel(&hippo_client, channel, app).await?;
+                let rule = range_rule
+                    .clone()
+                    .or_else(|| channel.range_rule.clone())
+                    .unwrap_or_else(|| channels::DEFAULT_RANGE_RULE.to_owned());
+                let req = match given {
+                    Some(req) => req,
+                    None => channels::parse_range_rule(&rule)?,
+                };
+                channels::warn_if_unmatched(&hippo_client, &channel.app_id, &rule, &req).await?;
+                let mut command = channels::update_command(&channel);
+                command.revision_selection_strategy =
+                    ChannelRevisionSelectionStrategy::UseRangeRule;
+                command.range_rule = Some(rule.clone());
+                command.active_revision_id = None;
+                hippo_client.update_channel(command).await?;
+                println!(
+                    "Unpinned channel {}: it now uses range rule '{}' (was {})",
+                    channel.name,
+                    rule,
+                    channels::describe_selection(&channel)
+                );
+            }
+
+            Commands::Channel(ChannelCommands::Promote {
+                from,
+                to,
+                copy_env,
+                yes,
+                app,
+            }) => {
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let source = resolve::channel(&hippo_client, from, app).await?;
+                let target = resolve::channel(&hippo_client, to, app).await?;
+                if source.id == target.id {
+                    anyhow::bail!("cannot promote channel {} to itself", source.name);
+                }
+                if source.app_id != target.app_id {
+                    anyhow::bail!(
+                        "channels {} and {} belong to different apps",
+                        source.name,
+                        target.name
+                    );
+                }
+                let revision = match &source.active_revision {
+                    Some(r) => r.as_ref().clone(),
+                    None => anyhow::bail!("channel {} is not serving any revision", source.name),
+                };
+                let plan = if *copy_env {
+                    Some(env_vars::plan_copy(
+                        env_vars::list_for_channel(&hippo_client, &source.id).await?,
+                        env_vars::list_for_channel(&hippo_client, &target.id).await?,
+                        &env_vars::KeyFilter::new(&[], &[])?,
+                        true,
+                    ))
+                } else {
+                    None
+                };
+                let env_changes = plan.as_ref().map_or(0, |p| p.changes.len());
+                let already_pinned = target.revision_selection_strategy
+                    == ChannelRevisionSelectionStrategy::UseSpecifiedRevision
+                    && target.active_revision.as_ref().map(|r| &r.id) == Some(&revision.id);
+                if already_pinned && env_changes == 0 {
+                    println!(
+                        "Channel {} is already pinned to revision {}",
+                        target.name, revision.revision_number
+                    );
+                    return Ok(());
+                }
+
+                let masker = env_vars::Masker::new(false, &hippo_conf.secret_keys)?;
+                println!("Promoting {} to {}:", source.name, target.name);
+                println!(
+                    "  revision: {} -> pinned revision {}",
+                    channels::describe_selection(&target),
+                    revision.revision_number
+                );
+                if let Some(plan) = &plan {
+                    println!("  environment variables: {} change(s)", env_changes);
+                    for change in &plan.changes {
+                        println!("    {}", change.describe(&masker));
+                    }
+                }
+                if !*yes && !std::io::stdin().is_terminal() {
+                    anyhow::bail!("pass --yes to promote without confirmation");
+                }
+                if !*yes
+                    && !Confirm::new()
+                        .with_prompt(format!("Promote {} to {}?", source.name, target.name))
+                        .default(false)
+                        .interact()?
+                {
+                    anyhow::bail!("promotion cancelled");
+                }
+
+                if let Some(plan) = &plan {
+                    let failures = env_vars::apply(&hippo_client, &target.id, &plan.changes).await;
+                    if !failures.is_empty() {
+                        for (index, error) in &failures {
+                            eprintln!(
+                                "{} (FAILED: {})",
+                                plan.changes[*index].describe(&masker),
+                                error
+                            );
+                        }
+                        anyhow::bail!(
+                            "{} environment variable change(s) could not be made, so the revision was not changed",
+                            failures.len()
+                        );
+                    }
+                }
+                channels::pin(&hippo_client, &target, &revision).await?;
+                println!(
+                    "Promoted {} to {}: now pinned to revision {}",
+                    source.name, target.name, revision.revision_number
+                );
+            }
+
+            Commands::Channel(ChannelCommands::Rollback {
+                channel: name,
+                to,
+                app,
+            }) => {
+                let app_arg = app.as_deref();
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let channel = resolve::channel(&hippo_client, name, app).await?;
+                let current = channel.active_revision.as_deref();
+                let revision = match (to, current) {
+                    (Some(to), _) => resolve::revision(&hippo_client, &channel.app_id, to).await?,
+                    (None, Some(current)) => {
+                        let revisions = hippo_client.list_revisions().await?.revisions;
+                        channels::previous_revision(&revisions, &channel.app_id, current)?
+                            .cloned()
+                            .ok_or_else(|| {
+                                anyhow::anyhow!(
+                                    "the app has no release older than {}",
+                                    current.revision_number
+                                )
+                            })?
+                    }
+                    (None, None) => anyhow::bail!(
+                        "channel {} is not serving any revision, so there is nothing to roll back from; pass --to",
+                        channel.name
+                    ),
+                };
+                if current.is_some_and(|c| c.id == revision.id) {
+                    anyhow::bail!(
+                        "channel {} is already serving revision {}",
+                        channel.name,
+                        revision.revision_number
+                    );
+                }
+                channels::pin(&hippo_client, &channel, &revision).await?;
+                println!(
+                    "Rolled back channel {} to revision {} (was {})",
+                    channel.name,
+                    revision.revision_number,
+                    channels::describe_selection(&channel)
+                );
+                if let Some(undo) = channels::undo_command(&channel, name, app_arg) {
+                    println!("To undo the rollback, run: {}", undo);
+                }
+            }
+
+            Commands::Channel(ChannelCommands::Wait {
+                channel,
+                revision,
+                timeout,
+                interval,
+                probe,
+                app,
+            }) => {
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let channel = resolve::channel(&hippo_client, channel, app).await?;
+                let revision = resolve::revision(&hippo_client, &channel.app_id, revision).await?;
+                let prober = if *probe {
+                    Some(probe::Prober::new(hippo_conf.danger_accept_invalid_certs)?)
+                } else {
+                    None
+                };
+                let served = wait::until_serving(
+                    &hippo_client,
+                    &channel,
+                    &revision,
+                    prober.as_ref(),
+                    humantime::parse_duration(timeout)?,
+                    humantime::parse_duration(interval)?,
+                )
+                .await?;
+                if !served {
+                    eprintln!(
+                        "Error: timed out after {} waiting for channel {} to serve revision {}",
+                        timeout, channel.name, revision.revision_number
+                    );
+                    std::process::exit(wait::TIMEOUT_EXIT_CODE);
+                }
+                println!(
+                    "Channel {} is serving revision {}",
+                    channel.name, revision.revision_number
+                );
+            }
+
+            Commands::Channel(ChannelCommands::Probe {
+                channel,
+                paths,
+                status,
+                body,
+                platform_domain,
+                app,
+            }) => {
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let channel = resolve::channel(&hippo_client, channel, app).await?;
+                let url = probe::channel_url(&channel, platform_domain.as_deref())?;
+                let prober = probe::Prober::new(hippo_conf.danger_accept_invalid_certs)?;
+                let mut results = vec![];
+                for path in paths {
+                    let check = probe::Check {
+                        path: path.clone(),
+                        status: *status,
+                        body: body.clone(),
+                    };
+                    results.push(prober.run(&url, &check).await);
+                }
+                probe::print(&results, output)?;
+                let failed = results.iter().filter(|r| r.error.is_some()).count();
+                if failed > 0 {
+                    anyhow::bail!(
+                        "{} of {} check(s) of channel {} failed",
+                        failed,
+                        results.len(),
+                        channel.name
+                    );
+                }
+            }
+
+            Commands::Channel(ChannelCommands::Resolve {
+                channel_or_rule,
+                app,
+            }) => {
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let revisions = hippo_client.list_revisions().await?.revisions;
+                let resolution = match resolve::channel(&hippo_client, channel_or_rule, app).await {
+                    Ok(channel) => channels::resolve_channel(&revisions, &channel)?,
+                    Err(not_a_channel) => {
+                        let app = match app {
+                            Some(a) => resolve::app(&hippo_client, a).await?,
+                            None => anyhow::bail!("no app given for the range rule, and no default app is configured (see 'hippo config set default_app')"),
+                        };
+                        channels::resolve_rule(&revisions, &app.id, channel_or_rule).map_err(
+                            |e| {
+                                e.context(format!(
+                                    "'{}' is neither a channel nor a valid range rule ({})",
+                                    channel_or_rule, not_a_channel
+                                ))
+                            },
+                        )?
+                    }
+                };
+                resolution.print(output)?;
+            }
+
             Commands::Channel(ChannelCommands::Remove { id }) => {
                 hippo_client.remove_channel(id.to_owned()).await?;
                 println!("Removed Channel {}", id);
             }
 
+            Commands::Config(ConfigCommands::View {}) => match output {
+                OutputFormat::Json => {
+                    let mut view = hippo_conf.redacted()?;
+                    for key in ConfigKey::value_variants() {
+                        if let Some(value) = project.get(*key) {
+                            let name = key.to_possible_value().unwrap();
+                            view[name.get_name()] = value.into();
+                        }
+                    }
+                    if let Some(path) = &project.path {
+                        view["project_config"] = path.display().to_string().into();
+                    }
+                    println!("{}", serde_json::to_string_pretty(&view)?);
+                }
+                OutputFormat::Text => {
+                    for key in ConfigKey::value_variants() {
+                        let name = key.to_possible_value().unwrap();
+                        match (project.get(*key), hippo_conf.get(*key)) {
+                            (Some(value), _) => println!(
+                                "{} = {} (from {})",
+                                name.get_name(),
+                                value,
+                                project.path.as_ref().unwrap().display()
+                            ),
+                            (None, Some(value)) => println!("{} = {}", name.get_name(), value),
+                            (None, None) => println!("{} is not set", name.get_name()),
+                        }
+                    }
+                    match hippo_conf
+                        .token_info
+                        .as_ref()
+                        .and_then(|t| t.token.as_ref())
+                    {
+                        Some(_) => println!("token = {}", config::REDACTED),
+                        None => println!("token is not set"),
+                    }
+                }
+            },
+
+            Commands::Config(ConfigCommands::Get { key }) => match project
+                .get(*key)
+                .or_else(|| hippo_conf.get(*key))
+            {
+                Some(value) => println!("{}", value),
+                None => anyhow::bail!("{} is not set", key.to_possible_value().unwrap().get_name()),
+            },
+
+            Commands::Config(ConfigCommands::Set { key, value }) => {
+                HippoConfig::update(&hippo_config_path, |c| c.set(*key, value))?;
+                println!(
+                    "Set {} to {}",
+                    key.to_possible_value().unwrap().get_name(),
+                    value
+                );
+                if let (Some(_), Some(path)) = (project.get(*key), &project.path) {
+                    println!("Note: this setting is overridden by {}", path.display());
+                }
+            }
+
+            Commands::Config(ConfigCommands::Unset { key }) => {
+                HippoConfig::update(&hippo_config_path, |c| c.unset(*key))?;
+                println!("Reset {}", key.to_possible_value().unwrap().get_name());
+            }
+
+            Commands::Config(ConfigCommands::Path {}) => {
+                println!("{}", hippo_config_path.display());
+                if let Some(path) = &project.path {
+                    println!("{} (project)", path.display());
+                }
+            }
+
             Commands::Env(EnvCommands::Add {
                 key,
                 value,
                 channel_id,
+                channel,
+                value_from_file,
+                value_stdin,
+                value_from_env,
             }) => {
+                let read_value = env_vars::read_value(
+                    value_from_file.as_deref(),
+                    *value_stdin,
+                    value_from_env.as_deref(),
+                )?;
+                let value = match (read_value, value) {
+                    (Some(_), Some(_)) => anyhow::bail!(
+                        "the value was given both as an argument and with a --value-* option (give the channel with --channel)"
+                    ),
+                    (Some(v), None) => v,
+                    (None, Some(v)) => v.to_owned(),
+                    (None, None) => anyhow::bail!(
+                        "no value given; pass it as an argument or with --value-from-file, --value-stdin or --value-from-env"
+                    ),
+                };
+                let channel = resolve::channel_or_default(
+                    &hippo_client,
+                    channel_id.as_deref().or(channel.as_deref()),
+                    default_channel.as_deref(),
+                    default_app.as_deref(),
+                )
+                .await?;
                 let id = hippo_client
-                    .add_environment_variable(
-                        key.to_owned(),
-                        value.to_owned(),
-                        channel_id.to_owned(),
-                    )
+                    .add_environment_variable(key.to_owned(), value, channel.id)
                     .await?;
                 println!("Added Environment Variable {} (ID = '{}')", key, id);
                 println!("IMPORTANT: save this Environment Variable ID for later - you will need it to update and/or delete the Environment Variable");
             }
 
-            Commands::Env(EnvCommands::List { }) => {
-                let envs = hippo_client.list_environmentvariables().await?;
+            Commands::Env(EnvCommands::Copy {
+                from,
+                to,
+                only,
+                exclude,
+                overwrite,
+                dry_run,
+                reveal,
+                app,
+            }) => {
+                let masker = env_vars::Masker::new(*reveal, &hippo_conf.secret_keys)?;
+                let filter = env_vars::KeyFilter::new(only, exclude)?;
+                let app = app.as_ref().or(default_app.as_ref()).map(|a| a.as_str());
+                let source = resolve::channel(&hippo_client, from, app).await?;
+                let target = resolve::channel(&hippo_client, to, app).await?;
+                if source.id == target.id {
+                    anyhow::bail!("cannot copy environment variables from a channel to itself");
+                }
+                let plan = env_vars::plan_copy(
+                    env_vars::list_for_channel(&hippo_client, &source.id).await?,
+                    env_vars::list_for_channel(&hippo_client, &target.id).await?,
+                    &filter,
+                    *overwrite,
+                );
+                let failures = if *dry_run {
+                    Default::default()
+                } else {
+                    env_vars::apply(&hippo_client, &target.id, &plan.changes).await
+                };
+                plan.print(&target, output, *dry_run, &failures, &masker)?;
+                if !failures.is_empty() {
+                    anyhow::bail!(
+                        "{} environment variable change(s) could not be made",
+                        failures.len()
+                    );
+                }
+            }
+
+            Commands::Env(EnvCommands::Export {
+                channel,
+                format,
+                file,
+                app,
+            }) => {
+                let channel = resolve::channel_or_default(
+                    &hippo_client,
+                    channel.as_deref(),
+                    default_channel.as_deref(),
+                    app.as_ref().or(default_app.as_ref()).map(|a| a.as_str()),
+                )
+                .await?;
+                let vars = env_vars::list_for_channel(&hippo_client, &channel.id).await?;
+                let masker = env_vars::Masker::new(true, &hippo_conf.secret_keys)?;
+                let (secret, exported): (Vec<_>, Vec<_>) =
+                    vars.iter().partition(|v| masker.is_secret(&v.key));
+                let (unnamed, exported): (Vec<_>, Vec<_>) = exported.into_iter().partition(|v| {
+                    *format == ExportFormat::Shell && !env_vars::is_shell_name(&v.key)
+                });
+                let text = env_vars::export(&exported, *format)?;
+                if !secret.is_empty() {
+                    let keys: Vec<&str> = secret.iter().map(|v| v.key.as_str()).collect();
+                    eprintln!(
+                        "Warning: left out secret keys, whose values are never exported: {}",
+                        keys.join(", ")
+                    );
+                }
+                if !unnamed.is_empty() {
+                    let keys: Vec<&str> = unnamed.iter().map(|v| v.key.as_str()).collect();
+                    eprintln!(
+                        "Warning: left out keys that are not valid shell variable names: {}",
+                        keys.join(", ")
+                    );
+                }
+                match file {
+                    Some(path) => {
+                        fs::write(path, text)
+                            .with_context(|| format!("failed to write {}", path.display()))?;
+                        println!(
+                            "Exported {} environment variable(s) from channel {} to {}",
+                            exported.len(),
+                            channel.name,
+                            path.display()
+                        );
+                    }
+                    None => print!("{}", text),
+                }
+            }
+
+            Commands::Env(EnvCommands::Import { channel, file, app }) => {
+                let channel = resolve::channel_or_default(
+                    &hippo_client,
+                    channel.as_deref(),
+                    default_channel.as_deref(),
+                    app.as_ref().or(default_app.as_ref()).map(|a| a.as_str()),
+                )
+                .await?;
+                let text = fs::read_to_string(file)
+                    .with_context(|| format!("failed to read {}", file.display()))?;
+                let entries = dotenv::parse(&text)
+                    .with_context(|| format!("failed to parse {}", file.display()))?;
+                env_vars::check_not_masked(&entries)?;
+                let report = env_vars::import(&hippo_client, &channel, entries).await?;
+                report.print(&channel, output)?;
+                if !report.failed.is_empty() {
+                    anyhow::bail!(
+                        "{} environment variable(s) could not be created",
+                        report.failed.len()
+                    );
+                }
+            }
+
+            Commands::Env(EnvCommands::Sync {
+                channel,
+                file,
+                prune,
+                dry_run,
+                reveal,
+                app,
+            }) => {
+                let masker = env_vars::Masker::new(*reveal, &hippo_conf.secret_keys)?;
+                let channel = resolve::channel_or_default(
+                    &hippo_client,
+                    channel.as_deref(),
+                    default_channel.as_deref(),
+                    app.as_ref().or(default_app.as_ref()).map(|a| a.as_str()),
+                )
+                .await?;
+                let text = fs::read_to_string(file)
+                    .with_context(|| format!("failed to read {}", file.display()))?;
+                let entries = dotenv::parse(&text)
+                    .with_context(|| format!("failed to parse {}", file.display()))?;
+                env_vars::check_not_masked(&entries)?;
+                let current = env_vars::list_for_channel(&hippo_client, &channel.id).await?;
+                let plan = env_vars::plan_sync(current, entries, *prune);
+                let failures = if *dry_run {
+                    Default::default()
+                } else {
+                    env_vars::apply(&hippo_client, &channel.id, &plan.changes).await
+                };
+                plan.print(&channel, output, *dry_run, &failures, &masker)?;
+                if !failures.is_empty() {
+                    anyhow::bail!(
+                        "{} environment variable change(s) could not be made",
+                        failures.len()
+                    );
+                }
+            }
+
+            Commands::Env(EnvCommands::List { reveal }) => {
+                let masker = env_vars::Masker::new(*reveal, &hippo_conf.secret_keys)?;
+                let mut envs = hippo_client.list_environmentvariables().await?;
+                for env in envs.environment_variables.iter_mut() {
+                    env.value = masker.show(&env.key, &env.value);
+                }
                 println!("{}", serde_json::to_string_pretty(&envs.environment_variables)?);
             }
 
@@ -223,6 +1002,7 @@ impl Cli {
                 username,
                 password,
                 danger_accept_invalid_certs,
+                credential_helper,
             } => {
                 let h_username: String = match username {
                     Some(u) => u.to_owned(),
@@ -238,26 +1018,173 @@ impl Cli {
                     api_key: None,
                 });
                 let token = hippo_client.login(h_username.clone(), h_password).await?;
-                hippo_conf.danger_accept_invalid_certs = *danger_accept_invalid_certs;
-                hippo_conf.username = h_username;
-                hippo_conf.url = url.to_owned();
-                hippo_conf.token_info = Some(token);
-                if !hippo_config_path.exists() && hippo_config_path.ancestors().count() != 0 {
-                    fs::create_dir_all(hippo_config_path.parent().unwrap())?;
-                }
-                serde_json::to_writer(File::create(hippo_config_path)?, &hippo_conf)?;
-                println!("Logged in as {}", hippo_conf.username);
+                let credential_helper = credential_helper
+                    .clone()
+                    .or_else(|| hippo_conf.credential_helper.clone());
+                let token_info = match &credential_helper {
+                    Some(program) => {
+                        CredentialHelper::new(program)
+                            .store(url, &h_username, &token)
+                            .await?;
+                        None
+                    }
+                    None => Some(token),
+                };
+                HippoConfig::update(&hippo_config_path, |c| {
+                    c.credential_helper = credential_helper;
+                    c.danger_accept_invalid_certs = *danger_accept_invalid_certs;
+                    c.username = h_username.clone();
+                    c.url = url.to_owned();
+                    c.token_info = token_info;
+                    Ok(())
+                })?;
+                println!("Logged in as {}", h_username);
             }
 
             Commands::Logout {} => {
-                hippo_conf = Default::default();
-                if !hippo_config_path.exists() && hippo_config_path.ancestors().count() != 0 {
-                    fs::create_dir_all(hippo_config_path.parent().unwrap())?;
+                let credential_helper = hippo_conf.credential_helper.take();
+                if let Some(program) = &credential_helper {
+                    CredentialHelper::new(program)
+                        .erase(&hippo_conf.url, &hippo_conf.username)
+                        .await?;
                 }
-                serde_json::to_writer(File::create(hippo_config_path)?, &hippo_conf)?;
+                HippoConfig::update(&hippo_config_path, |c| {
+                    *c = HippoConfig {
+                        credential_helper,
+                        ..Default::default()
+                    };
+                    Ok(())
+                })?;
                 println!("Logged out");
             }
 
+            Commands::Preview(PreviewCommands::Up {
+                revision,
+                branch,
+                app,
+            }) => {
+                let app =
+                    resolve::app_or_default(&hippo_client, app.as_deref(), default_app.as_deref())
+                        .await?;
+                let branch = preview::branch_or_current(branch.as_deref())?;
+                let name = preview::channel_name(&branch)?;
+                let revision = resolve::revision(&hippo_client, &app.id, revision).await?;
+                let existing = hippo_client
+                    .list_channels()
+                    .await?
+                    .channels
+                    .into_iter()
+                    .find(|c| c.app_id == app.id && c.name == name);
+                let id = match existing {
+                    Some(channel) => {
+                        channels::pin(&hippo_client, &channel, &revision).await?;
+                        println!(
+                            "Updated preview channel {} for branch {}: now pinned to revision {} (was {})",
+                            name,
+                            branch,
+                            revision.revision_number,
+                            channels::describe_selection(&channel)
+                        );
+                        channel.id
+                    }
+                    None => {
+                        let id = hippo_client
+                            .add_channel(
+                                app.id.clone(),
+                                name.clone(),
+                                None,
+                                ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
+                                None,
+                                Some(revision.id.clone()),
+                                None,
+                            )
+                            .await?;
+                        println!(
+                            "Added preview channel {} for branch {}, pinned to revision {} (ID = '{}')",
+                            name, branch, revision.revision_number, id
+                        );
+                        id
+                    }
+                };
+                let channel = hippo_client.get_channel_by_id(&id).await?;
+                println!("URL: {}/", probe::channel_url(&channel, None)?);
+            }
+
+            Commands::Preview(PreviewCommands::Down { branch, app }) => {
+                let app =
+                    resolve::app_or_default(&hippo_client, app.as_deref(), default_app.as_deref())
+                        .await?;
+                let branch = preview::branch_or_current(branch.as_deref())?;
+                let name = preview::channel_name(&branch)?;
+                let existing = hippo_client
+                    .list_channels()
+                    .await?
+                    .channels
+                    .into_iter()
+                    .find(|c| c.app_id == app.id && c.name == name);
+                match existing {
+                    Some(channel) => {
+                        hippo_client.remove_channel(channel.id).await?;
+                        println!("Removed preview channel {} for branch {}", name, branch);
+                    }
+                    None => println!("Branch {} has no preview channel", branch),
+                }
+            }
+
+            Commands::Preview(PreviewCommands::Gc {
+                merged,
+                base,
+                dry_run,
+                yes,
+                app,
+            }) => {
+                let app =
+                    resolve::app_or_default(&hippo_client, app.as_deref(), default_app.as_deref())
+                        .await?;
+                let live = preview::live_channel_names(merged.then(|| base.as_str()))?;
+                let channels = hippo_client.list_channels().await?.channels;
+                let stale = preview::stale(&channels, &app.id, &live);
+                if stale.is_empty() {
+                    println!("No preview channels to remove");
+                    return Ok(());
+                }
+                if *dry_run {
+                    for channel in stale {
+                        println!("Would remove preview channel {}", channel.name);
+                    }
+                    return Ok(());
+                }
+
+                println!("Preview channels whose branches are gone:");
+                for channel in &stale {
+                    println!("  {}", channel.name);
+                }
+                if !*yes && !std::io::stdin().is_terminal() {
+                    anyhow::bail!("pass --yes to remove them without confirmation");
+                }
+                if !*yes
+                    && !Confirm::new()
+                        .with_prompt(format!("Remove {} preview channel(s)?", stale.len()))
+                        .default(false)
+                        .interact()?
+                {
+                    anyhow::bail!("removal cancelled");
+                }
+                let mut failed = 0;
+                for channel in stale {
+                    match hippo_client.remove_channel(channel.id.clone()).await {
+                        Ok(()) => println!("Removed preview channel {}", channel.name),
+                        Err(e) => {
+                            eprintln!("Failed to remove preview channel {}: {}", channel.name, e);
+                            failed += 1;
+                        }
+                    }
+                }
+                if failed > 0 {
+                    anyhow::bail!("{} preview channel(s) could not be removed", failed);
+                }
+            }
+
             Commands::Register {
                 url,
                 username,
@@ -292,6 +1219,17 @@ impl Cli {
                     .add_revision(app_storage_id.to_owned(), revision_number.to_owned())
                     .await?;
                 println!("Added Revision {}", revision_number);
+                if let Some(note) = revision_number::prerelease_note(revision_number) {
+                    eprintln!("{}", note);
+                }
+            }
+
+            Commands::Revision(RevisionCommands::Version { hippofacts }) => {
+                let number = revision_number::generate(hippofacts, chrono::Utc::now())?;
+                println!("{}", number);
+                if let Some(note) = revision_number::prerelease_note(&number.to_string()) {
+                    eprintln!("{}", note);
+                }
             }
 
             Commands::Revision(RevisionCommands::List {}) => {
@@ -300,7 +1238,18 @@ impl Cli {
             }
 
             Commands::Whoami {} => {
-                println!("{}", hippo_conf.username);
+                let session = Session::check(
+                    &hippo_client,
+                    &hippo_conf.url,
+                    &profile_name,
+                    &hippo_conf.username,
+                    token_info.as_ref(),
+                )
+                .await;
+                session.print(output)?;
+                if session.status != SessionStatus::Valid {
+                    anyhow::bail!("the session for profile '{}' is not valid", profile_name);
+                }
             }
         }
 
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+
+use hippo_openapi::models::ChannelDto;
+use sha2::{Digest, Sha256};
+
+use super::git;
+
+/// Preview channels are named after their branch, with this prefix so that they
+/// can be told apart from other channels.
+const CHANNEL_PREFIX: &str = "preview-";
+
+/// The longest a channel name can be and still be a label in the channel's domain.
+const MAX_CHANNEL_NAME_LEN: usize = 63;
+
+/// How many hex digits of the branch name's hash go at the end of a channel name.
+const HASH_LEN: usize = 6;
+
+/// The name of the preview channel for a branch: the prefix followed by the branch
+/// name, lowercased, with anything other than letters and digits turned into dashes,
+/// and a short hash of the branch name itself, so that branches whose names only
+/// differ in those characters, or after the name is cut short, get channels of their
+/// own.
+pub(crate) fn channel_name(branch: &str) -> anyhow::Result<String> {
+    let mut slug = String::new();
+    for c in branch.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.truncate(MAX_CHANNEL_NAME_LEN - CHANNEL_PREFIX.len() - HASH_LEN - 1);
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        anyhow::bail!(
+            "branch '{}' has no letters or digits to name a channel after",
+            branch
+        );
+    }
+    let digest = Sha256::digest(branch.as_bytes());
+    let hash: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("{}{}-{}", CHANNEL_PREFIX, slug, &hash[..HASH_LEN]))
+}
+
+/// The branch that is checked out, or the one given on the command line.
+pub(crate) fn branch_or_current(given: Option<&str>) -> anyhow::Result<String> {
+    if let Some(branch) = given {
+        return Ok(branch.to_owned());
+    }
+    match git::run(&["rev-parse", "--abbrev-ref", "HEAD"])?.trim() {
+        "HEAD" => anyhow::bail!("no branch is checked out; pass --branch"),
+        branch => Ok(branch.to_owned()),
+    }
+}
+
+/// Whether a channel name has the shape of a preview channel name, so that channels
+/// that merely start with the prefix, such as `preview-docs`, are left alone.
+pub(crate) fn is_preview_channel(name: &str) -> bool {
+    let (slug, hash) = match name
+        .strip_prefix(CHANNEL_PREFIX)
+        .and_then(|rest| rest.rsplit_once('-'))
+    {
+        Some(parts) => parts,
+        None => return false,
+    };
+    hash.len() == HASH_LEN
+        && hash.chars().all(|c| matches!(c, '0'..='9' | 'a'..='f'))
+        && slug.split('-').all(|part| {
+            !part.is_empty() && part.chars().all(|c| matches!(c, 'a'..='z' | '0'..='9'))
+        })
+}
+
+/// The names of the preview channels whose branches still exist, locally or on a
+/// remote. With `merged_into`, branches that have been merged into that branch
+/// don't count, except for that branch itself.
+///
+/// A checkout that only has some of the branches, such as the shallow, single-branch
+/// clones CI systems make, would make every other branch look deleted, so this fails
+/// unless the repository is a full clone with remote-tracking branches.
+pub(crate) fn live_channel_names(merged_into: Option<&str>) -> anyhow::Result<HashSet<String>> {
+    if git::run(&["rev-parse", "--is-shallow-repository"])?.trim() == "true" {
+        anyhow::bail!(
+            "the repository is a shallow clone, so it can't tell which branches still exist; fetch the whole history and all branches first (e.g. `git fetch --unshallow origin '+refs/heads/*:refs/remotes/origin/*'`)"
+        );
+    }
+    if git::run(&["for-each-ref", "--count=1", "refs/remotes"])?
+        .trim()
+        .is_empty()
+    {
+        anyhow::bail!(
+            "the repository has no remote-tracking branches, so it can't tell which branches still exist on the remote; fetch them first (e.g. `git fetch origin`)"
+        );
+    }
+    let merged = match merged_into {
+        Some(base) => branches(&["--merged", base])?,
+        None => vec![],
+    };
+    Ok(branches(&[])?
+        .into_iter()
+        .filter(|b| !merged.contains(b) || Some(b.as_str()) == merged_into)
+        .filter_map(|b| channel_name(&b).ok())
+        .collect())
+}
+
+/// The app's preview channels that are not in `live`. Only channels named like
+/// preview channels count.
+pub(crate) fn stale<'a>(
+    channels: &'a [ChannelDto],
+    app_id: &str,
+    live: &HashSet<String>,
+) -> Vec<&'a ChannelDto> {
+    channels
+        .iter()
+        .filter(|c| c.app_id == app_id && is_preview_channel(&c.name))
+        .filter(|c| !live.contains(&c.name))
+        .collect()
+}
+
+/// Local and remote branch names, without the remote name, so a branch that exists
+/// in both places appears twice.
+fn branches(filter: &[&str]) -> anyhow::Result<Vec<String>> {
+    let mut args = vec!["for-each-ref", "--format=%(refname)"];
+    args.extend_from_slice(filter);
+    args.extend_from_slice(&["refs/heads", "refs/remotes"]);
+    Ok(git::run(&args)?
+        .lines()
+        .filter_map(|r| {
+            r.strip_prefix("refs/heads/").or_else(|| {
+                r.strip_prefix("refs/remotes/")
+                    .and_then(|r| r.split_once('/'))
+                    .map(|(_, branch)| branch)
+            })
+        })
+        .filter(|b| *b != "HEAD")
+        .map(str::to_owned)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channel_name_is_a_slug_of_the_branch_with_a_hash() {
+        let name = channel_name("feature/login-page").unwrap();
+        assert!(name.starts_with("preview-feature-login-page-"), "{}", name);
+        assert_eq!(name.len(), "preview-feature-login-page-".len() + HASH_LEN);
+        assert_eq!(channel_name("feature/login-page").unwrap(), name);
+        assert!(channel_name("///").is_err());
+    }
+
+    #[test]
+    fn channel_name_tells_apart_branches_with_the_same_slug() {
+        let names: HashSet<_> = [
+            "feature/login-page",
+            "feature-login-page",
+            "Feature_Login.Page",
+        ]
+        .iter()
+        .map(|b| channel_name(b).unwrap())
+        .collect();
+        assert_eq!(names.len(), 3);
+    }
+
+    #[test]
+    fn channel_name_fits_in_a_domain_label_and_tells_apart_long_branches() {
+        let long = "a".repeat(100);
+        let first = channel_name(&format!("{}-1", long)).unwrap();
+        let second = channel_name(&format!("{}-2", long)).unwrap();
+        assert_eq!(first.len(), MAX_CHANNEL_NAME_LEN);
+        assert_ne!(first, second);
+
+        let name = channel_name(&format!("{}/{}", "a".repeat(47), "b")).unwrap();
+        assert!(name.len() <= MAX_CHANNEL_NAME_LEN);
+        assert!(!name.contains("--"), "{}", name);
+    }
+
+    #[test]
+    fn is_preview_channel_only_matches_generated_names() {
+        assert!(is_preview_channel(
+            &channel_name("feature/login-page").unwrap()
+        ));
+        assert!(is_preview_channel(&channel_name(&"a".repeat(100)).unwrap()));
+        for name in [
+            "preview-docs",
+            "preview-a0e5be",
+            "preview-docs-a0e5bg",
+            "preview-docs-A0E5BE",
+            "preview--a0e5be",
+            "preview-Docs-a0e5be",
+            "production",
+        ] {
+            assert!(!is_preview_channel(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn stale_leaves_out_live_other_apps_and_hand_made_channels() {
+        let channel = |name: &str, app_id: &str| ChannelDto {
+            id: name.to_owned(),
+            app_id: app_id.to_owned(),
+            name: name.to_owned(),
+            ..Default::default()
+        };
+        let live_name = channel_name("main").unwrap();
+        let gone_name = channel_name("feature/gone").unwrap();
+        let channels = [
+            channel(&live_name, "app"),
+            channel(&gone_name, "app"),
+            channel(&channel_name("feature/other").unwrap(), "other-app"),
+            channel("preview-docs", "app"),
+        ];
+        let live = HashSet::from([live_name]);
+        let stale: Vec<_> = stale(&channels, "app", &live)
+            .into_iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(stale, [gone_name.as_str()]);
+    }
+}
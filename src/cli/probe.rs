@@ -0,0 +1,131 @@
+use std::time::{Duration, Instant};
+
+use hippo_openapi::models::ChannelDto;
+use regex::Regex;
+use serde::Serialize;
+
+use super::commands::OutputFormat;
+
+/// How long a probe waits for the channel to respond.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Makes HTTP requests to the apps that channels serve, as opposed to the Hippo API.
+pub(crate) struct Prober {
+    client: reqwest::Client,
+}
+
+/// A request to make to a channel and what its response must look like.
+#[derive(Default)]
+pub(crate) struct Check {
+    pub path: String,
+    /// The status the response must have; any success status if not given.
+    pub status: Option<u16>,
+    /// A pattern the response body must match.
+    pub body: Option<Regex>,
+}
+
+/// The outcome of a check.
+#[derive(Serialize)]
+pub(crate) struct ProbeResult {
+    pub url: String,
+    pub status: Option<u16>,
+    pub latency_ms: u128,
+    pub error: Option<String>,
+}
+
+impl Prober {
+    pub(crate) fn new(danger_accept_invalid_certs: bool) -> anyhow::Result<Self> {
+        let client = reqwest::Client::builder()
+            .danger_accept_invalid_certs(danger_accept_invalid_certs)
+            .timeout(PROBE_TIMEOUT)
+            .build()?;
+        Ok(Self { client })
+    }
+
+    /// Makes the check's request to the channel at `base_url`. The latency includes
+    /// reading the body.
+    pub(crate) async fn run(&self, base_url: &str, check: &Check) -> ProbeResult {
+        let url = format!("{}/{}", base_url, check.path.trim_start_matches('/'));
+        let start = Instant::now();
+        let (status, error) = match self.client.get(&url).send().await {
+            Ok(response) => {
+                let status = response.status();
+                let body = response.text().await;
+                let error = match (check.status, &check.body, body) {
+                    (Some(expected), _, _) if status.as_u16() != expected => {
+                        Some(format!("expected status {}, got {}", expected, status))
+                    }
+                    (None, _, _) if !status.is_success() => {
+                        Some(format!("expected a success status, got {}", status))
+                    }
+                    (_, Some(_), Err(e)) => Some(format!("could not read the body: {}", e)),
+                    (_, Some(pattern), Ok(body)) if !pattern.is_match(&body) => {
+                        Some(format!("the body does not match /{}/", pattern))
+                    }
+                    _ => None,
+                };
+                (Some(status.as_u16()), error)
+            }
+            Err(e) => (None, Some(format!("no response: {}", e))),
+        };
+        ProbeResult {
+            url,
+            status,
+            latency_ms: start.elapsed().as_millis(),
+            error,
+        }
+    }
+}
+
+/// The URL a channel is served at: HTTPS if it has a certificate, HTTP otherwise.
+/// Channels without a domain of their own are served at
+/// `<channel>.<app>.<platform_domain>`.
+pub(crate) fn channel_url(
+    channel: &ChannelDto,
+    platform_domain: Option<&str>,
+) -> anyhow::Result<String> {
+    let domain = match (channel.domain.as_str(), &channel.app_summary, platform_domain) {
+        ("", Some(app), Some(platform_domain)) => {
+            format!("{}.{}.{}", channel.name, app.name, platform_domain)
+        }
+        ("", _, _) => anyhow::bail!(
+            "channel {} has no domain; pass --platform-domain to use the default <channel>.<app>.<platform_domain>",
+            channel.name
+        ),
+        (domain, _, _) => domain.to_owned(),
+    };
+    let scheme = if channel.certificate.is_some() {
+        "https"
+    } else {
+        "http"
+    };
+    Ok(format!("{}://{}", scheme, domain))
+}
+
+/// Prints the results of a channel's checks.
+pub(crate) fn print(results: &[ProbeResult], output: OutputFormat) -> anyhow::Result<()> {
+    match output {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(results)?),
+        OutputFormat::Text => {
+            for result in results {
+                let status = result
+                    .status
+                    .map_or_else(|| "---".to_owned(), |s| s.to_string());
+                let line = format!(
+                    "{} {} {} {:>5}ms  {}",
+                    if result.error.is_none() {
+                        "ok  "
+                    } else {
+                        "FAIL"
+                    },
+                    status,
+                    result.url,
+                    result.latency_ms,
+                    result.error.as_deref().unwrap_or_default()
+                );
+                println!("{}", line.trim_end());
+            }
+        }
+    }
+    Ok(())
+}
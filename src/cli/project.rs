@@ -0,0 +1,97 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use super::commands::config::Key;
+
+/// The name of the project config file, searched for in the working directory
+/// and its ancestors.
+pub(crate) const PROJECT_CONFIG_FILE: &str = ".hippo";
+
+/// Per-project settings, read from a TOML `.hippo` file. Anything set here takes
+/// precedence over the global config file, but not over command line arguments.
+/// Keys this version doesn't know, e.g. from a file written for a newer one, are
+/// ignored with a warning.
+#[derive(Default, Deserialize)]
+pub(crate) struct ProjectConfig {
+    /// The login profile to use
+    pub profile: Option<String>,
+    /// The default application (ID or name)
+    pub app: Option<String>,
+    /// The default channel (ID or name)
+    pub channel: Option<String>,
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, toml::Value>,
+}
+
+impl ProjectConfig {
+    /// Looks for a project config file in the working directory and then in each of
+    /// its parents. Returns an empty config if there is none.
+    pub(crate) fn discover() -> anyhow::Result<Self> {
+        let cwd = std::env::current_dir()?;
+        match cwd
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+        {
+            Some(path) => Self::load(&path),
+            None => Ok(Default::default()),
+        }
+    }
+
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("failed to read project config {}", path.display()))?;
+        let mut config: Self = toml::from_str(&text)
+            .with_context(|| format!("failed to parse project config {}", path.display()))?;
+        for key in config.unknown.keys() {
+            eprintln!(
+                "Warning: ignoring unknown setting '{}' in project config {}",
+                key,
+                path.display()
+            );
+        }
+        config.path = Some(path.to_owned());
+        Ok(config)
+    }
+
+    /// The project's value for a global setting, if it overrides it.
+    pub(crate) fn get(&self, key: Key) -> Option<String> {
+        match key {
+            Key::DefaultApp => self.app.clone(),
+            Key::DefaultChannel => self.channel.clone(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_ignores_unknown_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&path, "app = \"helloworld\"\nfuture_setting = 1\n").unwrap();
+        let config = ProjectConfig::load(&path).unwrap();
+        assert_eq!(config.get(Key::DefaultApp).as_deref(), Some("helloworld"));
+        assert!(config.unknown.contains_key("future_setting"));
+        assert_eq!(config.path.as_deref(), Some(path.as_path()));
+    }
+
+    #[test]
+    fn load_rejects_invalid_values_of_known_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(PROJECT_CONFIG_FILE);
+        fs::write(&path, "app = 1\n").unwrap();
+        assert!(ProjectConfig::load(&path).is_err());
+    }
+}
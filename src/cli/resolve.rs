@@ -0,0 +1,122 @@
+use hippo_openapi::models::{AppDto, CertificateDto, ChannelDto, RevisionDto};
+
+use crate::client::Client;
+
+/// Finds an app by its ID or, failing that, by its name.
+pub(crate) async fn app(client: &Client, id_or_name: &str) -> anyhow::Result<AppDto> {
+    let apps = client.list_apps().await?.apps;
+    if let Some(app) = apps.iter().find(|a| a.id == id_or_name) {
+        return Ok(app.clone());
+    }
+    let mut matches = apps.into_iter().filter(|a| a.name == id_or_name);
+    match (matches.next(), matches.next()) {
+        (Some(app), None) => Ok(app),
+        (None, _) => anyhow::bail!("no app has the ID or name '{}'", id_or_name),
+        (Some(_), Some(_)) => anyhow::bail!(
+            "more than one app is named '{}'; use the app ID instead",
+            id_or_name
+        ),
+    }
+}
+
+/// Finds the app given on the command line, or else the configured default app.
+pub(crate) async fn app_or_default(
+    client: &Client,
+    given: Option<&str>,
+    default: Option<&str>,
+) -> anyhow::Result<AppDto> {
+    match given.or(default) {
+        Some(a) => app(client, a).await,
+        None => anyhow::bail!(
+            "no app given, and no default app is configured (see 'hippo config set default_app')"
+        ),
+    }
+}
+
+/// Finds a channel by its ID or, failing that, by its name. If `app` is given, only
+/// channels of that app (ID or name) are matched by name.
+pub(crate) async fn channel(
+    client: &Client,
+    id_or_name: &str,
+    app: Option<&str>,
+) -> anyhow::Result<ChannelDto> {
+    let channels = client.list_channels().await?.channels;
+    if let Some(channel) = channels.iter().find(|c| c.id == id_or_name) {
+        return Ok(channel.clone());
+    }
+    let app_id = match app {
+        Some(a) => Some(self::app(client, a).await?.id),
+        None => None,
+    };
+    let mut matches = channels
+        .into_iter()
+        .filter(|c| c.name == id_or_name && app_id.as_ref().is_none_or(|id| &c.app_id == id));
+    match (matches.next(), matches.next()) {
+        (Some(channel), None) => Ok(channel),
+        (None, _) => anyhow::bail!("no channel has the ID or name '{}'", id_or_name),
+        (Some(_), Some(_)) => anyhow::bail!(
+            "more than one channel is named '{}'; specify the app or use the channel ID instead",
+            id_or_name
+        ),
+    }
+}
+
+/// Finds the channel given on the command line, or else the configured default channel.
+pub(crate) async fn channel_or_default(
+    client: &Client,
+    given: Option<&str>,
+    default: Option<&str>,
+    app: Option<&str>,
+) -> anyhow::Result<ChannelDto> {
+    match given.or(default) {
+        Some(c) => channel(client, c, app).await,
+        None => anyhow::bail!(
+            "no channel given, and no default channel is configured (see 'hippo config set default_channel')"
+        ),
+    }
+}
+
+/// Finds a certificate by its ID or, failing that, by its name.
+pub(crate) async fn certificate(
+    client: &Client,
+    id_or_name: &str,
+) -> anyhow::Result<CertificateDto> {
+    let certificates = client.list_certificates().await?.certificates;
+    if let Some(certificate) = certificates.iter().find(|c| c.id == id_or_name) {
+        return Ok(certificate.clone());
+    }
+    let mut matches = certificates.into_iter().filter(|c| c.name == id_or_name);
+    match (matches.next(), matches.next()) {
+        (Some(certificate), None) => Ok(certificate),
+        (None, _) => anyhow::bail!("no certificate has the ID or name '{}'", id_or_name),
+        (Some(_), Some(_)) => anyhow::bail!(
+            "more than one certificate is named '{}'; use the certificate ID instead",
+            id_or_name
+        ),
+    }
+}
+
+/// Finds one of an app's revisions by its ID or, failing that, by its revision number.
+pub(crate) async fn revision(
+    client: &Client,
+    app_id: &str,
+    id_or_number: &str,
+) -> anyhow::Result<RevisionDto> {
+    let revisions = client.list_revisions().await?.revisions;
+    revisions
+        .iter()
+        .filter(|r| r.app_id == app_id)
+        .find(|r| r.id == id_or_number)
+        .or_else(|| {
+            revisions
+                .iter()
+                .find(|r| r.app_id == app_id && r.revision_number == id_or_number)
+        })
+        .cloned()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "the app has no revision with the ID or number '{}'",
+                id_or_number
+            )
+        })
+}
@@ -0,0 +1,153 @@
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use semver::Version;
+use serde::Deserialize;
+
+use super::git;
+
+/// The parts of a HIPPOFACTS file that revision numbers are made from.
+#[derive(Deserialize)]
+struct Hippofacts {
+    bindle: BindleSection,
+}
+
+#[derive(Deserialize)]
+struct BindleSection {
+    version: String,
+}
+
+/// Makes a revision number from the version in a HIPPOFACTS file and the state of
+/// the git repository it is in: `<version>-dev.<timestamp>+<describe>`, e.g.
+/// `1.2.0-dev.20220601120000123+v1.1.0.5.gabc1234` (a version that is already a
+/// prerelease gets `.dev.<timestamp>` appended instead). The timestamp, down to the
+/// millisecond, makes revision numbers for the same version sort in the order they
+/// were made; the build metadata, from `git describe`, records the nearest tag, the
+/// commits since it, the commit and whether there were uncommitted changes, but does
+/// not affect ordering.
+pub(crate) fn generate(hippofacts: &Path, now: DateTime<Utc>) -> anyhow::Result<Version> {
+    let base = base_version(hippofacts)?;
+    let dir = match hippofacts.parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let describe = git::run_in(
+        dir,
+        &["describe", "--tags", "--long", "--always", "--dirty"],
+    )?;
+    compose(&base, now, describe.trim())
+}
+
+/// Explains, for a revision number that is a prerelease, that range rules don't
+/// select it by default.
+pub(crate) fn prerelease_note(revision_number: &str) -> Option<String> {
+    match Version::parse(revision_number) {
+        Ok(version) if version.is_prerelease() => Some(format!(
+            "Note: {} is a prerelease, which range rules such as `*` or `^{}.{}` do not match unless they name a prerelease of the same version (e.g. `>={}.{}.{}-0`); pin a channel to it to serve it",
+            revision_number,
+            version.major,
+            version.minor,
+            version.major,
+            version.minor,
+            version.patch
+        )),
+        _ => None,
+    }
+}
+
+fn compose(base: &Version, now: DateTime<Utc>, describe: &str) -> anyhow::Result<Version> {
+    let separator = if base.pre.is_empty() { '-' } else { '.' };
+    let mut number = format!("{}{}dev.{}", base, separator, now.format("%Y%m%d%H%M%S%3f"));
+    number.push('+');
+    number.push_str(&build_metadata(describe));
+    Version::parse(&number)
+        .with_context(|| format!("generated revision number '{}' is not valid semver", number))
+}
+
+/// Reads `bindle.version` from a HIPPOFACTS file, without any build metadata.
+fn base_version(path: &Path) -> anyhow::Result<Version> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))?;
+    let facts: Hippofacts =
+        toml::from_str(&text).with_context(|| format!("failed to parse {}", path.display()))?;
+    let mut version = Version::parse(&facts.bindle.version).with_context(|| {
+        format!(
+            "the bindle version '{}' in {} is not a semver version",
+            facts.bindle.version,
+            path.display()
+        )
+    })?;
+    version.build.clear();
+    Ok(version)
+}
+
+/// Turns `git describe` output, e.g. `v1.1.0-5-gabc1234-dirty` or just `abc1234`,
+/// into dot-separated semver build identifiers.
+fn build_metadata(describe: &str) -> String {
+    describe
+        .split(['.', '-'])
+        .map(|part| {
+            part.chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+                .collect::<String>()
+        })
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(millis: i64) -> DateTime<Utc> {
+        Utc.timestamp_millis_opt(1_654_084_800_000 + millis)
+            .unwrap()
+    }
+
+    #[test]
+    fn number_has_a_millisecond_timestamp_and_describe_as_build_metadata() {
+        let base = Version::parse("1.2.0").unwrap();
+        let number = compose(&base, at(7), "v1.1.0-5-gabc1234-dirty").unwrap();
+        assert_eq!(
+            number.to_string(),
+            "1.2.0-dev.20220601120000007+v1.1.0.5.gabc1234.dirty"
+        );
+
+        let base = Version::parse("2.0.0-beta.1").unwrap();
+        let number = compose(&base, at(0), "abc1234").unwrap();
+        assert_eq!(
+            number.to_string(),
+            "2.0.0-beta.1.dev.20220601120000000+abc1234"
+        );
+    }
+
+    #[test]
+    fn numbers_made_in_the_same_second_sort_in_order() {
+        let base = Version::parse("1.2.0").unwrap();
+        let first = compose(&base, at(1), "abc1234").unwrap();
+        let second = compose(&base, at(2), "abc1234").unwrap();
+        assert!(first < second);
+    }
+
+    #[test]
+    fn build_metadata_keeps_only_valid_identifiers() {
+        assert_eq!(build_metadata("v1.1.0-5-gabc1234"), "v1.1.0.5.gabc1234");
+        assert_eq!(
+            build_metadata("release/1.0--2-g1f_3"),
+            "release-1.0.2.g1f-3"
+        );
+        assert_eq!(build_metadata("abc1234"), "abc1234");
+    }
+
+    #[test]
+    fn prerelease_note_is_only_for_prereleases() {
+        assert!(prerelease_note("1.2.0").is_none());
+        assert!(prerelease_note("not-semver").is_none());
+        let note = prerelease_note("1.2.0-dev.20220601120000000+abc1234").unwrap();
+        assert!(note.contains("`*`"), "{}", note);
+        assert!(note.contains(">=1.2.0-0"), "{}", note);
+    }
+}
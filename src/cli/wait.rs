@@ -0,0 +1,61 @@
+use std::time::{Duration, Instant};
+
+use hippo_openapi::models::{ChannelDto, RevisionDto};
+
+use super::probe::{self, Check, ProbeResult, Prober};
+use crate::client::Client;
+
+/// The exit code of `hippo channel wait` when it gives up, the same as `timeout(1)`'s.
+pub(crate) const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// Polls a channel until it serves `revision` and, if there is a prober, until the
+/// channel's URL responds successfully. Returns false if that doesn't happen within
+/// `timeout`. Progress is reported on stderr whenever something changes.
+pub(crate) async fn until_serving(
+    client: &Client,
+    channel: &ChannelDto,
+    revision: &RevisionDto,
+    prober: Option<&Prober>,
+    timeout: Duration,
+    interval: Duration,
+) -> anyhow::Result<bool> {
+    let start = Instant::now();
+    let url = match prober {
+        Some(_) => probe::channel_url(channel, None)?,
+        None => String::new(),
+    };
+    let mut last_status = String::new();
+    loop {
+        let status = match client.get_channel_by_id(&channel.id).await {
+            Ok(current) => match current.active_revision {
+                Some(active) if active.id == revision.id => match prober {
+                    Some(prober) => match prober.run(&url, &Check::default()).await {
+                        ProbeResult { error: None, .. } => return Ok(true),
+                        ProbeResult {
+                            url,
+                            error: Some(e),
+                            ..
+                        } => format!("serving {}, but {}: {}", revision.revision_number, url, e),
+                    },
+                    None => return Ok(true),
+                },
+                Some(active) => format!("serving {}", active.revision_number),
+                None => "not serving any revision".to_owned(),
+            },
+            Err(e) => format!("could not be read: {}", e),
+        };
+        if status != last_status {
+            eprintln!(
+                "[{:>4}s] channel {} is {}",
+                start.elapsed().as_secs(),
+                channel.name,
+                status
+            );
+            last_status = status;
+        }
+        if start.elapsed() + interval > timeout {
+            return Ok(false);
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
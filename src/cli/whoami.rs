@@ -0,0 +1,139 @@
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Utc};
+use hippo_openapi::models::TokenInfo;
+use serde::Serialize;
+
+use super::commands::OutputFormat;
+use crate::client::Client;
+
+/// What the server made of our token when `whoami` asked it.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SessionStatus {
+    Valid,
+    Expired,
+    Rejected,
+    Unreachable,
+    LoggedOut,
+}
+
+/// Everything `whoami` knows about the current login.
+#[derive(Serialize)]
+pub(crate) struct Session {
+    pub url: String,
+    pub profile: String,
+    pub username: String,
+    pub expiration: Option<DateTime<Utc>>,
+    pub expires_in_seconds: Option<i64>,
+    pub status: SessionStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Session {
+    /// Builds the session report, asking the server whether it still accepts the token.
+    pub(crate) async fn check(
+        client: &Client,
+        url: &str,
+        profile: &str,
+        username: &str,
+        token_info: Option<&TokenInfo>,
+    ) -> Self {
+        let expiration = token_info
+            .and_then(|t| t.expiration.as_deref())
+            .and_then(parse_expiration);
+        let expires_in = expiration.map(|e| e - Utc::now());
+        let has_token = token_info.is_some_and(|t| t.token.is_some());
+
+        let (status, error) = if username.is_empty() || !has_token {
+            (SessionStatus::LoggedOut, None)
+        } else if expires_in.is_some_and(|d| d <= Duration::zero()) {
+            (SessionStatus::Expired, None)
+        } else {
+            match client.validate_token().await {
+                Ok(true) => (SessionStatus::Valid, None),
+                Ok(false) => (SessionStatus::Rejected, None),
+                Err(e) => (SessionStatus::Unreachable, Some(e.to_string())),
+            }
+        };
+
+        Self {
+            url: url.to_owned(),
+            profile: profile.to_owned(),
+            username: username.to_owned(),
+            expiration,
+            expires_in_seconds: expires_in.map(|d| d.num_seconds()),
+            status,
+            error,
+        }
+    }
+
+    pub(crate) fn print(&self, output: OutputFormat) -> anyhow::Result<()> {
+        match output {
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(self)?),
+            OutputFormat::Text => {
+                println!("Server:   {}", self.url);
+                println!("Profile:  {}", self.profile);
+                if self.username.is_empty() {
+                    println!("Username: (not logged in)");
+                } else {
+                    println!("Username: {}", self.username);
+                }
+                match (self.expiration, self.expires_in_seconds) {
+                    (Some(e), Some(s)) if s > 0 => println!(
+                        "Token:    expires {} (in {})",
+                        e.format("%Y-%m-%d %H:%M:%S UTC"),
+                        format_duration(Duration::seconds(s))
+                    ),
+                    (Some(e), Some(s)) => println!(
+                        "Token:    expired {} ({} ago)",
+                        e.format("%Y-%m-%d %H:%M:%S UTC"),
+                        format_duration(Duration::seconds(-s))
+                    ),
+                    _ => println!("Token:    no expiry information"),
+                }
+                let status = match self.status {
+                    SessionStatus::Valid => "valid".to_owned(),
+                    SessionStatus::Expired => "expired - run 'hippo login'".to_owned(),
+                    SessionStatus::Rejected => {
+                        "rejected by the server - run 'hippo login'".to_owned()
+                    }
+                    SessionStatus::Unreachable => format!(
+                        "could not be checked: {}",
+                        self.error.as_deref().unwrap_or("server unreachable")
+                    ),
+                    SessionStatus::LoggedOut => "not logged in - run 'hippo login'".to_owned(),
+                };
+                println!("Session:  {}", status);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Parses the token expiry returned by Hippo. The server sends ISO 8601 timestamps,
+/// sometimes without an offset, in which case they are UTC.
+pub(crate) fn parse_expiration(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .map(|d| d.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+                .map(|d| Utc.from_utc_datetime(&d))
+        })
+        .ok()
+}
+
+/// Formats a duration for humans, e.g. `2d 3h 15m`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    let days = duration.num_days();
+    let hours = duration.num_hours() % 24;
+    let minutes = duration.num_minutes() % 60;
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}s", duration.num_seconds())
+    }
+}
@@ -6,11 +6,13 @@ use hippo_openapi::apis::certificate_api::{
     api_certificate_get, api_certificate_id_delete, api_certificate_post,
 };
 use hippo_openapi::apis::channel_api::{
-    api_channel_channel_id_get, api_channel_get, api_channel_id_delete, api_channel_post,
+    api_channel_channel_id_get, api_channel_get, api_channel_id_delete, api_channel_id_put,
+    api_channel_post,
 };
 use hippo_openapi::apis::configuration::{ApiKey, Configuration};
 use hippo_openapi::apis::environment_variable_api::{
-    api_environmentvariable_get, api_environmentvariable_id_delete, api_environmentvariable_post,
+    api_environmentvariable_get, api_environmentvariable_id_delete, api_environmentvariable_id_put,
+    api_environmentvariable_post,
 };
 use hippo_openapi::apis::revision_api::{api_revision_get, api_revision_post};
 use hippo_openapi::apis::Error;
@@ -18,10 +20,11 @@ use hippo_openapi::models::{
     AppsVm, CertificatesVm, ChannelDto, ChannelRevisionSelectionStrategy, ChannelsVm,
     CreateAccountCommand, CreateAppCommand, CreateCertificateCommand, CreateChannelCommand,
     CreateEnvironmentVariableCommand, CreateTokenCommand, EnvironmentVariablesVm,
-    RegisterRevisionCommand, RevisionsVm, TokenInfo,
+    RegisterRevisionCommand, RevisionsVm, TokenInfo, UpdateChannelCommand,
+    UpdateEnvironmentVariableCommand,
 };
 
-use reqwest::header;
+use reqwest::{header, StatusCode};
 use serde::Deserialize;
 
 const JSON_MIME_TYPE: &str = "application/json";
@@ -57,11 +60,9 @@ impl Client {
             basic_auth: None,
             oauth_access_token: None,
             bearer_access_token: None,
-            api_key: conn_info.api_key.map_or(None, |t| {
-                Some(ApiKey {
-                    prefix: Some("Bearer".to_owned()),
-                    key: t,
-                })
+            api_key: conn_info.api_key.map(|t| ApiKey {
+                prefix: Some("Bearer".to_owned()),
+                key: t,
             }),
         };
 
@@ -73,7 +74,7 @@ impl Client {
             &self.configuration,
             Some(CreateAccountCommand {
                 user_name: username,
-                password: password,
+                password,
             }),
         )
         .await
@@ -85,20 +86,32 @@ impl Client {
             &self.configuration,
             Some(CreateTokenCommand {
                 user_name: username,
-                password: password,
+                password,
             }),
         )
         .await
         .map_err(format_response_error)
     }
 
+    /// Makes an authenticated request to find out whether the server accepts our token.
+    /// Returns `Ok(false)` if the server rejects the token, and an error if the server
+    /// could not be reached or failed for some other reason.
+    pub async fn validate_token(&self) -> anyhow::Result<bool> {
+        match api_app_get(&self.configuration).await {
+            Ok(_) => Ok(true),
+            Err(Error::ResponseError(r))
+                if r.status == StatusCode::UNAUTHORIZED || r.status == StatusCode::FORBIDDEN =>
+            {
+                Ok(false)
+            }
+            Err(e) => Err(format_response_error(e)),
+        }
+    }
+
     pub async fn add_app(&self, name: String, storage_id: String) -> anyhow::Result<String> {
         api_app_post(
             &self.configuration,
-            Some(CreateAppCommand {
-                name: name,
-                storage_id: storage_id,
-            }),
+            Some(CreateAppCommand { name, storage_id }),
         )
         .await
         .map_err(format_response_error)
@@ -125,9 +138,9 @@ impl Client {
         api_certificate_post(
             &self.configuration,
             Some(CreateCertificateCommand {
-                name: name,
-                public_key: public_key,
-                private_key: private_key,
+                name,
+                public_key,
+                private_key,
             }),
         )
         .await
@@ -146,6 +159,7 @@ impl Client {
             .map_err(format_response_error)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_channel(
         &self,
         app_id: String,
@@ -157,8 +171,8 @@ impl Client {
         certificate_id: Option<String>,
     ) -> anyhow::Result<String> {
         let command = CreateChannelCommand {
-            app_id: app_id,
-            name: name,
+            app_id,
+            name,
             domain,
             revision_selection_strategy,
             range_rule,
@@ -170,7 +184,6 @@ impl Client {
             .map_err(format_response_error)
     }
 
-    #[allow(dead_code)]
     pub async fn get_channel_by_id(&self, id: &str) -> anyhow::Result<ChannelDto> {
         api_channel_channel_id_get(&self.configuration, id)
             .await
@@ -183,6 +196,14 @@ impl Client {
             .map_err(format_response_error)
     }
 
+    /// Replaces all of a channel's settings; see `cli::channels::update_command` for
+    /// building a command that keeps the settings that aren't being changed.
+    pub async fn update_channel(&self, command: UpdateChannelCommand) -> anyhow::Result<()> {
+        api_channel_id_put(&self.configuration, &command.id.clone(), Some(command))
+            .await
+            .map_err(format_response_error)
+    }
+
     pub async fn remove_channel(&self, id: String) -> anyhow::Result<()> {
         api_channel_id_delete(&self.configuration, &id)
             .await
@@ -198,9 +219,9 @@ impl Client {
         api_environmentvariable_post(
             &self.configuration,
             Some(CreateEnvironmentVariableCommand {
-                key: key,
-                value: value,
-                channel_id: channel_id,
+                key,
+                value,
+                channel_id,
             }),
         )
         .await
@@ -213,6 +234,25 @@ impl Client {
             .map_err(format_response_error)
     }
 
+    pub async fn update_environment_variable(
+        &self,
+        id: String,
+        key: String,
+        value: String,
+    ) -> anyhow::Result<()> {
+        api_environmentvariable_id_put(
+            &self.configuration,
+            &id,
+            Some(UpdateEnvironmentVariableCommand {
+                id: id.clone(),
+                key,
+                value,
+            }),
+        )
+        .await
+        .map_err(format_response_error)
+    }
+
     pub async fn remove_environment_variable(&self, id: String) -> anyhow::Result<()> {
         api_environmentvariable_id_delete(&self.configuration, &id)
             .await
@@ -227,8 +267,8 @@ impl Client {
         api_revision_post(
             &self.configuration,
             Some(RegisterRevisionCommand {
-                app_storage_id: app_storage_id,
-                revision_number: revision_number,
+                app_storage_id,
+                revision_number,
             }),
         )
         .await